@@ -2,7 +2,7 @@ use std::fmt;
 use std::io;
 use std::string::ToString;
 
-static INDENT_TOK: &'static str = "    ";
+static INDENT_TOK: &str = "    ";
 
 fn render_indent(f: &mut dyn io::Write, ctx: &RenderContext) -> Result<(), io::Error> {
     for _ in 0..ctx.indent_level {
@@ -29,9 +29,43 @@ macro_rules! render_ln {
     }
 }
 
+/// Writes a parenthesized, comma separated list of items following the `prefix_len` characters
+/// already written to the current line, and then `suffix` directly after the closing paren.
+///
+/// If the whole line wouldn't fit within the max width, each item goes on its own line instead,
+/// indented one level further than the line it continues.
+fn render_list(
+    f: &mut dyn io::Write,
+    ctx: &RenderContext,
+    prefix_len: usize,
+    items: &[String],
+    suffix: &str,
+) -> Result<(), io::Error> {
+    let joined = items.join(", ");
+    let line_len = ctx.indent_width() + prefix_len + joined.len() + "()".len() + suffix.len();
+
+    match ctx.max_width {
+        Some(max_width) if line_len > max_width && !items.is_empty() => {
+            writeln!(f, "(")?;
+            for (idx, item) in items.iter().enumerate() {
+                render_indent(f, &ctx.indented())?;
+                match idx + 1 < items.len() {
+                    true => writeln!(f, "{},", item)?,
+                    false => write!(f, "{}){}", item, suffix)?,
+                }
+            }
+            Ok(())
+        }
+        _ => write!(f, "({}){}", joined, suffix),
+    }
+}
+
 #[derive(Clone, Copy, Default)]
 pub struct RenderContext {
     indent_level: u8,
+
+    /// The column that lists are wrapped to stay within, if any
+    max_width: Option<usize>,
 }
 
 impl RenderContext {
@@ -41,6 +75,10 @@ impl RenderContext {
             ..*self
         }
     }
+
+    fn indent_width(&self) -> usize {
+        INDENT_TOK.len() * self.indent_level as usize
+    }
 }
 
 pub trait AstNode {
@@ -56,35 +94,53 @@ impl<T: fmt::Display> AstNode for T {
 pub struct Root {
     pub file_comment: Option<BlockComment>,
     pub using_statements: Vec<UsingStatement>,
+    pub using_aliases: Vec<UsingAlias>,
     pub children: Vec<Box<dyn AstNode>>,
+
+    /// The column to wrap long signatures and attributes before, if any
+    pub max_width: Option<usize>,
 }
 
 impl Root {
+    /// The using statements with duplicates removed, ordered with the System namespaces first and
+    /// then alphabetically.
+    fn sorted_usings(&self) -> Vec<&UsingStatement> {
+        let mut usings: Vec<_> = self.using_statements.iter().collect();
+        usings.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        usings.dedup_by(|a, b| a.path == b.path);
+        usings
+    }
+
     pub fn render(&self, f: &mut dyn io::Write) -> Result<(), io::Error> {
-        let ctx = RenderContext::default();
+        let ctx = RenderContext {
+            max_width: self.max_width,
+            ..RenderContext::default()
+        };
 
         let mut first = true;
 
-        match &self.file_comment {
-            Some(c) => {
-                c.render(f, ctx)?;
-                first = false;
-            }
-            None => (),
+        if let Some(c) = &self.file_comment {
+            c.render(f, ctx)?;
+            first = false;
         }
 
         if !first && !self.using_statements.is_empty() {
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
 
-        for using in &self.using_statements {
+        for using in self.sorted_usings() {
             using.render(f, ctx)?;
             first = false;
         }
 
+        for alias in &self.using_aliases {
+            alias.render(f, ctx)?;
+            first = false;
+        }
+
         for child in &self.children {
             if !first {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
 
             child.render(f, ctx)?;
@@ -103,7 +159,11 @@ impl AstNode for BlockComment {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
         render_ln!(f, &ctx, "/*")?;
         for line in &self.text {
-            render_ln!(f, &ctx, " * {}", line)?;
+            if line.is_empty() {
+                render_ln!(f, &ctx, " *")?;
+            } else {
+                render_ln!(f, &ctx, " * {}", line)?;
+            }
         }
         render_ln!(f, &ctx, " */")?;
 
@@ -111,16 +171,73 @@ impl AstNode for BlockComment {
     }
 }
 
+/// An xml documentation comment, eg `/// <remarks>...</remarks>`
+pub struct DocComment {
+    pub remarks: Vec<String>,
+}
+
+impl DocComment {
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+}
+
+impl AstNode for DocComment {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        if !self.remarks.is_empty() {
+            render_ln!(f, &ctx, "/// <remarks>")?;
+            for line in &self.remarks {
+                render_ln!(f, &ctx, "/// {}", DocComment::escape(line))?;
+            }
+            render_ln!(f, &ctx, "/// </remarks>")?;
+        }
+
+        Ok(())
+    }
+}
+
 pub struct UsingStatement {
     pub path: String,
 }
 
+impl UsingStatement {
+    fn sort_key(&self) -> (bool, &str) {
+        let is_system = self.path == "System" || self.path.starts_with("System.");
+        (!is_system, &self.path)
+    }
+}
+
 impl AstNode for UsingStatement {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
         render_ln!(f, &ctx, "using {};", self.path)
     }
 }
 
+/// Gives a type another name throughout the file, eg `using Half = System.UInt16;`
+pub struct UsingAlias {
+    pub alias: String,
+    pub target: String,
+
+    /// The preprocessor condition the alias is only defined under, if any
+    pub condition: Option<String>,
+}
+
+impl AstNode for UsingAlias {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        if let Some(condition) = &self.condition {
+            render_ln!(f, &ctx, "#if {}", condition)?;
+        }
+        render_ln!(f, &ctx, "using {} = {};", self.alias, self.target)?;
+        if self.condition.is_some() {
+            render_ln!(f, &ctx, "#endif")?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Renders its children between a pair of curly braces
 pub struct Scope {
     pub children: Vec<Box<dyn AstNode>>,
@@ -157,7 +274,7 @@ impl AstNode for Namespace {
         let mut first = true;
         for child in &self.children {
             if !first {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
             first = false;
 
@@ -188,6 +305,13 @@ pub enum CSharpType {
 
     Bool,
 
+    /// System.Half, which only exists from .NET 5 so may be an alias for UInt16 on older targets.
+    Half,
+    Single,
+    Double,
+
+    String,
+
     Array {
         elem_type: Box<CSharpType>,
     },
@@ -205,25 +329,58 @@ impl CSharpType {
     pub fn intptr() -> Self {
         Self::Struct { name: "IntPtr".into() }
     }
+
+    pub fn uintptr() -> Self {
+        Self::Struct { name: "UIntPtr".into() }
+    }
+
+    /// The name this type is written as wherever it appears in the generated source, eg in a
+    /// signature, a field, or the type argument of an attribute.
+    ///
+    /// Integers are named by their System types (`Int32`) rather than their keywords (`int`), so
+    /// that their width is always explicit.
+    pub fn csharp_type_name(&self) -> String {
+        match self {
+            CSharpType::Void => "void".to_string(),
+            CSharpType::SByte => "SByte".to_string(),
+            CSharpType::Int16 => "Int16".to_string(),
+            CSharpType::Int32 => "Int32".to_string(),
+            CSharpType::Int64 => "Int64".to_string(),
+            CSharpType::Byte => "Byte".to_string(),
+            CSharpType::UInt16 => "UInt16".to_string(),
+            CSharpType::UInt32 => "UInt32".to_string(),
+            CSharpType::UInt64 => "UInt64".to_string(),
+            CSharpType::Bool => "bool".to_string(),
+            CSharpType::Half => "Half".to_string(),
+            CSharpType::Single => "Single".to_string(),
+            CSharpType::Double => "Double".to_string(),
+            CSharpType::String => "string".to_string(),
+            CSharpType::Array { elem_type } => format!("{}[]", elem_type.csharp_type_name()),
+            CSharpType::Ptr { target } => format!("{}*", target.csharp_type_name()),
+            CSharpType::Struct { name } => name.to_string(),
+        }
+    }
+
+    /// The keyword naming this integer type, eg `int`, for the few places which only accept a
+    /// keyword, like the base of an enum.
+    pub fn integer_keyword(&self) -> Option<&'static str> {
+        match self {
+            CSharpType::SByte => Some("sbyte"),
+            CSharpType::Int16 => Some("short"),
+            CSharpType::Int32 => Some("int"),
+            CSharpType::Int64 => Some("long"),
+            CSharpType::Byte => Some("byte"),
+            CSharpType::UInt16 => Some("ushort"),
+            CSharpType::UInt32 => Some("uint"),
+            CSharpType::UInt64 => Some("ulong"),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for CSharpType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            CSharpType::Void => write!(f, "void"),
-            CSharpType::SByte => write!(f, "SByte"),
-            CSharpType::Int16 => write!(f, "Int16"),
-            CSharpType::Int32 => write!(f, "Int32"),
-            CSharpType::Int64 => write!(f, "Int64"),
-            CSharpType::Byte => write!(f, "Byte"),
-            CSharpType::UInt16 => write!(f, "UInt16"),
-            CSharpType::UInt32 => write!(f, "UInt32"),
-            CSharpType::UInt64 => write!(f, "UInt64"),
-            CSharpType::Bool => write!(f, "bool"),
-            CSharpType::Array { elem_type } => write!(f, "{}[]", elem_type),
-            CSharpType::Ptr { target } => write!(f, "{}*", target),
-            CSharpType::Struct { name } => write!(f, "{}", name),
-        }
+        f.write_str(&self.csharp_type_name())
     }
 }
 
@@ -251,14 +408,24 @@ impl fmt::Display for Ident {
 pub enum LiteralValue {
     QuotedString(String),
     EnumValue(String, String),
-    Number(i64),
+    Number(i128),
+
+    /// The Type object of the named type, eg `typeof(Foo)`
+    TypeOf(String),
 }
 
 impl fmt::Display for LiteralValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            LiteralValue::QuotedString(val) => write!(f, "\"{}\"", val),
+            LiteralValue::QuotedString(val) => {
+                let escaped = val
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                write!(f, "\"{}\"", escaped)
+            }
             LiteralValue::EnumValue(e, v) => write!(f, "{}.{}", e, v),
+            LiteralValue::TypeOf(ty) => write!(f, "typeof({})", ty),
             LiteralValue::Number(num) => write!(f, "{}", num),
         }
     }
@@ -268,6 +435,9 @@ pub struct Attribute {
     pub name: String,
     pub positional_parameters: Vec<LiteralValue>,
     pub named_parameters: Vec<(Ident, LiteralValue)>,
+
+    /// A preprocessor condition the attribute is only applied under, eg `NET7_0_OR_GREATER`
+    pub condition: Option<String>,
 }
 
 impl Attribute {
@@ -279,6 +449,39 @@ impl Attribute {
                 Ident("EntryPoint".to_string()),
                 LiteralValue::QuotedString(entrypoint.to_string()),
             )],
+            condition: None,
+        }
+    }
+
+    /// A marker attribute taking no parameters, eg `[DoesNotReturn]`
+    pub fn marker(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            positional_parameters: Vec::new(),
+            named_parameters: Vec::new(),
+            condition: None,
+        }
+    }
+
+    /// Marshals a field as the given UnmanagedType, eg `[MarshalAs(UnmanagedType.I1)]`
+    pub fn marshal_as(unmanaged_type: &str) -> Self {
+        Self {
+            name: "MarshalAs".to_string(),
+            positional_parameters: vec![LiteralValue::EnumValue(
+                "UnmanagedType".to_string(),
+                unmanaged_type.to_string(),
+            )],
+            named_parameters: Vec::new(),
+            condition: None,
+        }
+    }
+
+    /// Marshals a method's return value as the given UnmanagedType, eg
+    /// `[return: MarshalAs(UnmanagedType.I1)]`
+    pub fn return_marshal_as(unmanaged_type: &str) -> Self {
+        Self {
+            name: "return: MarshalAs".to_string(),
+            ..Self::marshal_as(unmanaged_type)
         }
     }
 
@@ -290,44 +493,42 @@ impl Attribute {
                 layout_kind.to_string(),
             )],
             named_parameters: Vec::new(),
+            condition: None,
         }
     }
 }
 
 impl AstNode for Attribute {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
-        render_indent(f, &ctx)?;
-        write!(f, "[{}", self.name)?;
-
-        if self.positional_parameters.len() + self.named_parameters.len() == 0 {
-            write!(f, "]\n")?;
-            return Ok(());
-        } else {
-            write!(f, "(")?;
+        if let Some(condition) = &self.condition {
+            render_ln!(f, &ctx, "#if {}", condition)?;
+            self.render_unconditionally(f, ctx)?;
+            return render_ln!(f, &ctx, "#endif");
         }
 
-        let mut first = true;
-        for param in &self.positional_parameters {
-            if !first {
-                write!(f, ", ")?;
-            }
-            first = false;
-
-            write!(f, "{}", param)?;
-        }
+        self.render_unconditionally(f, ctx)
+    }
+}
 
-        for (key, value) in &self.named_parameters {
-            if !first {
-                write!(f, ", ")?;
-            }
-            first = false;
+impl Attribute {
+    fn render_unconditionally(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        render_indent(f, &ctx)?;
+        let prefix = format!("[{}", self.name);
+        write!(f, "{}", prefix)?;
 
-            write!(f, "{} = {}", key, value)?;
+        if self.positional_parameters.len() + self.named_parameters.len() == 0 {
+            writeln!(f, "]")?;
+            return Ok(());
         }
 
-        write!(f, ")]\n")?;
+        let params: Vec<_> = self.positional_parameters
+            .iter()
+            .map(|param| param.to_string())
+            .chain(self.named_parameters.iter().map(|(key, value)| format!("{} = {}", key, value)))
+            .collect();
 
-        Ok(())
+        render_list(f, &ctx, prefix.len(), &params, "]")?;
+        writeln!(f)
     }
 }
 
@@ -339,7 +540,7 @@ impl AstNode for Statement {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
         render_indent(f, &ctx)?;
         self.expr.render(f, ctx)?;
-        write!(f, ";\n")
+        writeln!(f, ";")
     }
 }
 
@@ -354,6 +555,19 @@ impl AstNode for VariableDeclaration {
     }
 }
 
+/// Declares a pointer to a buffer allocated on the stack, eg `Byte* buf = stackalloc Byte[16];`
+pub struct StackAllocDeclaration {
+    pub name: Ident,
+    pub elem_ty: CSharpType,
+    pub len: usize,
+}
+
+impl AstNode for StackAllocDeclaration {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        render_ln!(f, &ctx, "{}* {} = stackalloc {}[{}];", self.elem_ty, self.name, self.elem_ty, self.len)
+    }
+}
+
 pub struct FieldAccess {
     pub element: Box<dyn AstNode>,
     pub field_name: Ident,
@@ -417,6 +631,24 @@ impl fmt::Display for Cast {
     }
 }
 
+/// Evaluates an expression in an explicitly `checked` or `unchecked` overflow context.
+pub struct OverflowContext {
+    pub checked: bool,
+    pub element: Box<dyn AstNode>,
+}
+
+impl fmt::Display for OverflowContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut elem_render_buf: Vec<u8> = Vec::new();
+        self.element.render(&mut elem_render_buf, RenderContext::default())
+            .map_err(|_| fmt::Error)?;
+        let rendered_elem = std::str::from_utf8(&elem_render_buf).expect("Rendered to invalid utf8!");
+
+        let keyword = if self.checked { "checked" } else { "unchecked" };
+        write!(f, "{}({})", keyword, rendered_elem)
+    }
+}
+
 pub struct BinaryExpression {
     pub lhs: Box<dyn AstNode>,
     pub rhs: Box<dyn AstNode>,
@@ -431,44 +663,79 @@ impl AstNode for BinaryExpression {
     }
 }
 
-pub struct TernaryExpression {
-    pub test: Box<dyn AstNode>,
-    pub true_branch: Box<dyn AstNode>,
-    pub false_branch: Box<dyn AstNode>,
+pub struct FixedAssignment {
+    pub ty: CSharpType,
+    pub id: Ident,
+    pub rhs: Box<dyn AstNode>,
 }
 
-impl AstNode for TernaryExpression {
+impl AstNode for FixedAssignment {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
-        write!(f, "( (")?;
-        self.test.render(f, ctx)?;
-        write!(f, ") ? (")?;
-        self.true_branch.render(f, ctx)?;
-        write!(f, ") : (")?;
-        self.false_branch.render(f, ctx)?;
-        write!(f, ") )")
+        render_indent(f, &ctx)?;
+
+        write!(f, "fixed ({} {} = ", self.ty, self.id)?;
+        self.rhs.render(f, ctx)?;
+        writeln!(f, ")")
     }
 }
 
-pub struct FixedAssignment {
+/// Declares a variable which is disposed of at the end of the following scope, eg
+/// `using (Foo foo = new Foo())`
+pub struct UsingResource {
     pub ty: CSharpType,
     pub id: Ident,
     pub rhs: Box<dyn AstNode>,
 }
 
-impl AstNode for FixedAssignment {
+impl AstNode for UsingResource {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
         render_indent(f, &ctx)?;
 
-        write!(f, "fixed ({} {} = ", self.ty, self.id)?;
+        write!(f, "using ({} {} = ", self.ty, self.id)?;
         self.rhs.render(f, ctx)?;
-        write!(f, ")\n")
+        writeln!(f, ")")
+    }
+}
+
+/// Constructs a new instance of a type, eg `new Foo(a, b)`
+pub struct ObjectCreation {
+    pub ty: CSharpType,
+    pub args: Vec<Box<dyn AstNode>>,
+}
+
+impl AstNode for ObjectCreation {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        write!(f, "new {}(", self.ty)?;
+        for (idx, arg) in self.args.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            arg.render(f, ctx)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A lambda taking no arguments, eg `() => Foo()`
+pub struct Lambda {
+    pub body: Box<dyn AstNode>,
+}
+
+impl fmt::Display for Lambda {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut body_render_buf: Vec<u8> = Vec::new();
+        self.body.render(&mut body_render_buf, RenderContext::default())
+            .map_err(|_| fmt::Error)?;
+        let rendered_body = std::str::from_utf8(&body_render_buf).expect("Rendered to invalid utf8!");
+
+        write!(f, "() => {}", rendered_body)
     }
 }
 
 pub struct MethodInvocation {
     pub target: Option<Ident>,
     pub method_name: Ident,
-    pub args: Vec<Ident>,
+    pub args: Vec<(Direction, Ident)>,
 }
 
 impl fmt::Display for MethodInvocation {
@@ -480,12 +747,15 @@ impl fmt::Display for MethodInvocation {
         write!(f, "{}(", self.method_name)?;
 
         let mut first = true;
-        for arg in &self.args {
+        for (direction, arg) in &self.args {
             if !first {
                 write!(f, ", ")?;
             }
             first = false;
 
+            if let Some(keyword) = direction.call_keyword() {
+                write!(f, "{} ", keyword)?;
+            }
             write!(f, "{}", arg)?;
         }
         write!(f, ")")
@@ -503,30 +773,243 @@ impl AstNode for ReturnStatement {
                 render_indent(f, &ctx)?;
                 write!(f, "return ")?;
                 v.render(f, ctx)?;
-                write!(f, ";\n")
+                writeln!(f, ";")
             }
             None => render_ln!(f, &ctx, "return;"),
         }
     }
 }
 
+/// Nodes which are only compiled under some preprocessor condition, eg `#if NET7_0_OR_GREATER`
+pub struct Conditional {
+    pub condition: String,
+    pub children: Vec<Box<dyn AstNode>>,
+}
+
+impl AstNode for Conditional {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        render_ln!(f, &ctx, "#if {}", self.condition)?;
+        for child in &self.children {
+            child.render(f, ctx)?;
+        }
+        render_ln!(f, &ctx, "#endif")
+    }
+}
+
+/// Verbatim lines of source, each rendered at the current indentation
+pub struct RawLines {
+    pub lines: Vec<String>,
+}
+
+impl RawLines {
+    /// Splits some source text into lines, stripping the indentation common to all of them.
+    pub fn dedented(text: &str) -> Self {
+        let lines: Vec<_> = text.lines().map(|l| l.trim_end()).collect();
+
+        let common_indent = lines
+            .iter()
+            .filter(|l| !l.is_empty())
+            .map(|l| l.len() - l.trim_start().len())
+            .min()
+            .unwrap_or(0);
+
+        let first = lines.iter().position(|l| !l.is_empty()).unwrap_or(lines.len());
+        let last = lines.iter().rposition(|l| !l.is_empty()).map_or(first, |l| l + 1);
+
+        Self {
+            lines: lines[first..last]
+                .iter()
+                .map(|l| l.get(common_indent..).unwrap_or("").to_string())
+                .collect(),
+        }
+    }
+}
+
+impl AstNode for RawLines {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        for line in &self.lines {
+            if line.is_empty() {
+                writeln!(f)?;
+            } else {
+                render_ln!(f, &ctx, "{}", line)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs its body only when the condition holds, eg `if (x) { .. }`
+pub struct IfStatement {
+    pub condition: Box<dyn AstNode>,
+    pub body: Vec<Box<dyn AstNode>>,
+}
+
+impl AstNode for IfStatement {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        render_indent(f, &ctx)?;
+        write!(f, "if (")?;
+        self.condition.render(f, ctx)?;
+        writeln!(f, ")")?;
+
+        render_ln!(f, &ctx, "{{")?;
+        for node in &self.body {
+            node.render(f, ctx.indented())?;
+        }
+        render_ln!(f, &ctx, "}}")
+    }
+}
+
+/// Throws a newly constructed exception, eg `throw new Exception("message");`
+pub struct ThrowStatement {
+    pub exception: Ident,
+    pub message: String,
+}
+
+impl AstNode for ThrowStatement {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        render_ln!(
+            f,
+            &ctx,
+            "throw new {}({});",
+            self.exception,
+            LiteralValue::QuotedString(self.message.clone())
+        )
+    }
+}
+
+/// A type pattern binding a new variable, eg `foo is Bar bar`
+pub struct IsPattern {
+    pub element: Box<dyn AstNode>,
+    pub ty: CSharpType,
+    pub binding: Ident,
+}
+
+impl AstNode for IsPattern {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        self.element.render(f, ctx)?;
+        write!(f, " is {} {}", self.ty, self.binding)
+    }
+}
+
+/// The logical negation of an expression, eg `!(foo)`
+pub struct Negation {
+    pub element: Box<dyn AstNode>,
+}
+
+impl AstNode for Negation {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        write!(f, "!(")?;
+        self.element.render(f, ctx)?;
+        write!(f, ")")
+    }
+}
+
+/// An access modifier, eg `public`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Internal,
+    Private,
+}
+
+impl Visibility {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Internal => "internal",
+            Visibility::Private => "private",
+        }
+    }
+}
+
+impl std::str::FromStr for Visibility {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Visibility::Public),
+            "internal" => Ok(Visibility::Internal),
+            "private" => Ok(Visibility::Private),
+            _ => Err("Visibility must be one of \"public\", \"internal\", or \"private\""),
+        }
+    }
+}
+
+/// How an argument is passed to a method
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// By readonly reference, eg `in Int32 foo`
+    In,
+    /// By reference, which the callee must write to, eg `out Int32 foo`
+    Out,
+    /// By reference, which the callee may read or write, eg `ref Int32 foo`
+    Ref,
+    /// By readonly reference, which unlike `in` must refer to a variable, eg `ref readonly Int32 foo`
+    RefReadonly,
+    /// A plain copy, eg `Int32 foo`
+    ByValue,
+}
+
+impl Direction {
+    /// The keyword marking this direction in a method's signature
+    pub fn keyword(self) -> Option<&'static str> {
+        match self {
+            Direction::In => Some("in"),
+            Direction::Out => Some("out"),
+            Direction::Ref => Some("ref"),
+            Direction::RefReadonly => Some("ref readonly"),
+            Direction::ByValue => None,
+        }
+    }
+
+    /// The keyword marking this direction at a method's call sites
+    pub fn call_keyword(self) -> Option<&'static str> {
+        match self {
+            Direction::RefReadonly => Some("in"),
+            _ => self.keyword(),
+        }
+    }
+}
+
 pub struct MethodArgument {
     pub name: Ident,
     pub ty: CSharpType,
+    pub direction: Direction,
+
+    /// Marks the first argument of an extension method
+    pub is_this: bool,
+
+    /// The UnmanagedType to marshal the argument as, eg `LPUTF8Str`
+    pub marshal_as: Option<String>,
 }
 
 impl AstNode for MethodArgument {
     fn render(&self, f: &mut dyn io::Write, _ctx: RenderContext) -> Result<(), io::Error> {
+        if let Some(unmanaged_type) = &self.marshal_as {
+            write!(f, "[MarshalAs(UnmanagedType.{})] ", unmanaged_type)?;
+        }
+
+        if self.is_this {
+            write!(f, "this ")?;
+        }
+
+        if let Some(keyword) = self.direction.keyword() {
+            write!(f, "{} ", keyword)?;
+        }
+
         write!(f, "{} {}", self.ty, self.name)
     }
 }
 
 pub struct Method {
+    pub doc: Option<DocComment>,
     pub attributes: Vec<Attribute>,
-    pub is_public: bool,
+    pub visibility: Visibility,
     pub is_static: bool,
     pub is_extern: bool,
     pub is_unsafe: bool,
+    pub is_override: bool,
     pub name: String,
     pub return_ty: CSharpType,
     pub args: Vec<MethodArgument>,
@@ -535,50 +1018,57 @@ pub struct Method {
 
 impl AstNode for Method {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        if let Some(doc) = &self.doc {
+            doc.render(f, ctx)?;
+        }
+
         for attr in &self.attributes {
             attr.render(f, ctx)?;
         }
 
-        render_indent(f, &ctx)?;
-        if self.is_public {
-            write!(f, "public ")?;
-        } else {
-            write!(f, "private ")?;
-        }
+        let mut signature = String::new();
+        signature.push_str(self.visibility.keyword());
+        signature.push(' ');
 
         if self.is_static {
-            write!(f, "static ")?;
+            signature.push_str("static ");
         }
 
         if self.is_extern {
-            write!(f, "extern ")?;
+            signature.push_str("extern ");
         }
 
         if self.is_unsafe {
-            write!(f, "unsafe ")?;
+            signature.push_str("unsafe ");
         }
 
-        write!(f, "{} {}(", self.return_ty, self.name)?;
+        if self.is_override {
+            signature.push_str("override ");
+        }
 
-        let mut first = true;
-        for arg in &self.args {
-            if !first {
-                write!(f, ", ")?;
-            }
-            first = false;
+        signature.push_str(&format!("{} {}", self.return_ty, self.name));
 
-            arg.render(f, ctx)?;
+        let mut args = Vec::new();
+        for arg in &self.args {
+            let mut arg_render_buf: Vec<u8> = Vec::new();
+            arg.render(&mut arg_render_buf, ctx)?;
+            args.push(String::from_utf8(arg_render_buf).expect("Rendered to invalid utf8!"));
         }
 
+        render_indent(f, &ctx)?;
+        write!(f, "{}", signature)?;
+
         let body = match &self.body {
             Some(b) => b,
             None => {
-                write!(f, ");\n")?;
+                render_list(f, &ctx, signature.len(), &args, ";")?;
+                writeln!(f)?;
                 return Ok(());
             }
         };
 
-        write!(f, ")\n")?;
+        render_list(f, &ctx, signature.len(), &args, "")?;
+        writeln!(f)?;
         render_ln!(f, &ctx, "{{")?;
         for node in body {
             node.render(f, ctx.indented())?;
@@ -590,13 +1080,62 @@ impl AstNode for Method {
 }
 
 pub struct Field {
+    pub attributes: Vec<Attribute>,
     pub name: String,
     pub ty: CSharpType,
+
+    /// If set, this field is a `const` with the given value
+    pub const_value: Option<LiteralValue>,
 }
 
 impl AstNode for Field {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
-        render_ln!(f, &ctx, "public {} {};", self.ty, self.name)
+        for attr in &self.attributes {
+            attr.render(f, ctx)?;
+        }
+
+        match &self.const_value {
+            Some(value) => render_ln!(f, &ctx, "public const {} {} = {};", self.ty, self.name, value),
+            None => render_ln!(f, &ctx, "public {} {};", self.ty, self.name),
+        }
+    }
+}
+
+pub struct Enum {
+    pub doc: Option<DocComment>,
+    pub attributes: Vec<Attribute>,
+    pub name: String,
+
+    /// The integral type backing this enum
+    pub underlying_ty: CSharpType,
+    pub variants: Vec<(String, i128)>,
+}
+
+impl AstNode for Enum {
+    fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        if let Some(doc) = &self.doc {
+            doc.render(f, ctx)?;
+        }
+
+        for attr in &self.attributes {
+            attr.render(f, ctx)?;
+        }
+
+        // The base of an enum must be given as a keyword, eg `byte` rather than `Byte`
+        let underlying_keyword = self.underlying_ty.integer_keyword().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} can't be the underlying type of an enum", self.underlying_ty),
+            )
+        })?;
+
+        render_ln!(f, &ctx, "public enum {} : {}", self.name, underlying_keyword)?;
+        render_ln!(f, &ctx, "{{")?;
+        let variant_ctx = ctx.indented();
+        for (name, value) in &self.variants {
+            render_ln!(f, &variant_ctx, "{} = {},", name, value)?;
+        }
+        render_ln!(f, &ctx, "}}")
     }
 }
 
@@ -606,33 +1145,62 @@ pub enum ObjectType {
 }
 
 pub struct Object {
+    pub doc: Option<DocComment>,
     pub attributes: Vec<Attribute>,
     pub object_type: ObjectType,
     pub is_static: bool,
     pub name: String,
+
+    /// The interfaces this object implements, eg `IEquatable<Foo>`
+    pub interfaces: Vec<String>,
     pub methods: Vec<Method>,
     pub fields: Vec<Field>,
+
+    /// Further methods, grouped into named `#region`s after the ungrouped ones
+    pub regions: Vec<Region>,
+
+    /// This is one of several `partial` declarations which together make up the object
+    pub is_partial: bool,
+}
+
+/// A named group of methods, which IDEs can collapse
+pub struct Region {
+    pub name: String,
+    pub methods: Vec<Method>,
 }
 
 impl AstNode for Object {
     fn render(&self, f: &mut dyn io::Write, ctx: RenderContext) -> Result<(), io::Error> {
+        if let Some(doc) = &self.doc {
+            doc.render(f, ctx)?;
+        }
+
         for attr in &self.attributes {
             attr.render(f, ctx)?;
         }
 
         let static_part = if self.is_static { "static " } else { "" };
+        let partial_part = if self.is_partial { "partial " } else { "" };
         let object_type = match self.object_type {
             ObjectType::Class => "class ",
             ObjectType::Struct => "struct ",
         };
 
+        let interfaces_part = if self.interfaces.is_empty() {
+            String::new()
+        } else {
+            format!(" : {}", self.interfaces.join(", "))
+        };
+
         render_ln!(
             f,
             &ctx,
-            "public {}{}{}",
+            "public {}{}{}{}{}",
             static_part,
+            partial_part,
             object_type,
-            self.name
+            self.name,
+            interfaces_part
         )?;
         render_ln!(f, &ctx, "{{")?;
 
@@ -645,15 +1213,213 @@ impl AstNode for Object {
 
         for method in &self.methods {
             if !first {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
             first = false;
 
             method.render(f, ctx.indented())?;
         }
 
+        // Region directives are conventionally written at column zero, like other preprocessor directives
+        for region in &self.regions {
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+
+            writeln!(f, "#region {}", region.name)?;
+            for (idx, method) in region.methods.iter().enumerate() {
+                if idx > 0 {
+                    writeln!(f)?;
+                }
+                method.render(f, ctx.indented())?;
+            }
+            writeln!(f, "#endregion")?;
+        }
+
         render_ln!(f, &ctx, "}}")?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_root(root: &Root) -> String {
+        let mut out = Vec::new();
+        root.render(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn render_node(node: &dyn AstNode, ctx: RenderContext) -> String {
+        let mut out = Vec::new();
+        node.render(&mut out, ctx).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn usings_are_deduped_and_sorted() {
+        let using = |path: &str| UsingStatement { path: path.into() };
+        let root = Root {
+            file_comment: None,
+            using_statements: vec![
+                using("TestLib"),
+                using("System.Runtime.InteropServices"),
+                using("Analytics"),
+                using("System"),
+                using("TestLib"),
+                using("System.Runtime.InteropServices"),
+                using("SystemExtensions"),
+            ],
+            using_aliases: Vec::new(),
+            children: Vec::new(),
+            max_width: None,
+        };
+
+        assert_eq!(
+            render_root(&root),
+            "using System;\n\
+             using System.Runtime.InteropServices;\n\
+             using Analytics;\n\
+             using SystemExtensions;\n\
+             using TestLib;\n"
+        );
+    }
+
+    #[test]
+    fn every_type_keeps_its_name() {
+        let boxed = |ty| Box::new(ty);
+        let cases = [
+            (CSharpType::Void, "void"),
+            (CSharpType::SByte, "SByte"),
+            (CSharpType::Int16, "Int16"),
+            (CSharpType::Int32, "Int32"),
+            (CSharpType::Int64, "Int64"),
+            (CSharpType::Byte, "Byte"),
+            (CSharpType::UInt16, "UInt16"),
+            (CSharpType::UInt32, "UInt32"),
+            (CSharpType::UInt64, "UInt64"),
+            (CSharpType::Bool, "bool"),
+            (CSharpType::Half, "Half"),
+            (CSharpType::Single, "Single"),
+            (CSharpType::Double, "Double"),
+            (CSharpType::String, "string"),
+            (CSharpType::Array { elem_type: boxed(CSharpType::Int32) }, "Int32[]"),
+            (CSharpType::Ptr { target: boxed(CSharpType::Byte) }, "Byte*"),
+            (CSharpType::Struct { name: "IntPtr".into() }, "IntPtr"),
+            (
+                CSharpType::Array {
+                    elem_type: boxed(CSharpType::Ptr {
+                        target: boxed(CSharpType::Struct { name: "Point".into() }),
+                    }),
+                },
+                "Point*[]",
+            ),
+        ];
+
+        for (ty, name) in &cases {
+            // Fails to compile when a variant is added, so that it gets a case here too
+            match ty {
+                CSharpType::Void | CSharpType::SByte | CSharpType::Int16 | CSharpType::Int32
+                | CSharpType::Int64 | CSharpType::Byte | CSharpType::UInt16 | CSharpType::UInt32
+                | CSharpType::UInt64 | CSharpType::Bool | CSharpType::Half | CSharpType::Single
+                | CSharpType::Double | CSharpType::String | CSharpType::Array { .. }
+                | CSharpType::Ptr { .. } | CSharpType::Struct { .. } => {}
+            }
+
+            assert_eq!(ty.csharp_type_name(), *name);
+            assert_eq!(ty.to_string(), *name);
+        }
+    }
+
+    #[test]
+    fn each_direction_renders_its_keyword() {
+        let arg = |direction| MethodArgument {
+            name: "value".into(),
+            ty: CSharpType::Int32,
+            direction,
+            is_this: false,
+            marshal_as: None,
+        };
+        let render = |direction| render_node(&arg(direction), RenderContext::default());
+
+        assert_eq!(render(Direction::In), "in Int32 value");
+        assert_eq!(render(Direction::Out), "out Int32 value");
+        assert_eq!(render(Direction::Ref), "ref Int32 value");
+        assert_eq!(render(Direction::RefReadonly), "ref readonly Int32 value");
+        assert_eq!(render(Direction::ByValue), "Int32 value");
+
+        // Readonly references are passed with `in` at the call site
+        assert_eq!(Direction::RefReadonly.call_keyword(), Some("in"));
+        assert_eq!(Direction::ByValue.call_keyword(), None);
+    }
+
+    fn extern_method() -> Method {
+        let arg = |name: &str| MethodArgument {
+            name: name.into(),
+            ty: CSharpType::Int32,
+            direction: Direction::ByValue,
+            is_this: false,
+            marshal_as: None,
+        };
+
+        Method {
+            doc: None,
+            attributes: vec![Attribute::dll_import("test_lib", "__bindgen_thunk_add_numbers")],
+            visibility: Visibility::Private,
+            is_static: true,
+            is_extern: true,
+            is_unsafe: false,
+            is_override: false,
+            name: "AddNumbers".to_string(),
+            return_ty: CSharpType::Int64,
+            args: vec![arg("first"), arg("second")],
+            body: None,
+        }
+    }
+
+    #[test]
+    fn signatures_within_the_width_stay_on_one_line() {
+        let ctx = RenderContext { max_width: Some(120), ..RenderContext::default() };
+        assert_eq!(
+            render_node(&extern_method(), ctx),
+            concat!(
+                "[DllImport(\"test_lib\", EntryPoint = \"__bindgen_thunk_add_numbers\")]\n",
+                "private static extern Int64 AddNumbers(Int32 first, Int32 second);\n",
+            )
+        );
+    }
+
+    #[test]
+    fn signatures_over_the_width_are_wrapped() {
+        let ctx = RenderContext { max_width: Some(60), ..RenderContext::default() };
+        assert_eq!(
+            render_node(&extern_method(), ctx),
+            concat!(
+                "[DllImport(\n",
+                "    \"test_lib\",\n",
+                "    EntryPoint = \"__bindgen_thunk_add_numbers\")]\n",
+                "private static extern Int64 AddNumbers(\n",
+                "    Int32 first,\n",
+                "    Int32 second);\n",
+            )
+        );
+    }
+
+    #[test]
+    fn enums_without_an_integer_base_fail_to_render() {
+        let colour = Enum {
+            doc: None,
+            attributes: Vec::new(),
+            name: "Colour".to_string(),
+            underlying_ty: CSharpType::Single,
+            variants: vec![("Red".to_string(), 1)],
+        };
+
+        let err = colour.render(&mut Vec::new(), RenderContext::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "Single can't be the underlying type of an enum");
+    }
+}
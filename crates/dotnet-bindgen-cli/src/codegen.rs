@@ -1,4 +1,6 @@
+use std::collections::BTreeMap;
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
 
 use heck::{CamelCase, MixedCase};
 
@@ -8,6 +10,53 @@ use crate::path_ext::BinBaseName;
 
 use dotnet_bindgen_core as core;
 
+/// The preprocessor condition under which the source generated marshalling types are available
+const NATIVE_MARSHALLING_CONDITION: &str = "NET7_0_OR_GREATER";
+
+/// The preprocessor condition under which System.Half doesn't exist, and so is aliased to its bits.
+const NO_HALF_CONDITION: &str = "!NET5_0_OR_GREATER";
+
+/// The preprocessor condition under which the runtime honours `[SkipLocalsInit]`
+const SKIP_LOCALS_INIT_CONDITION: &str = "NET5_0_OR_GREATER";
+
+/// The static class that bound functions are generated into, unless given one explicitly
+const DEFAULT_CLASS_NAME: &str = "TopLevelMethods";
+
+/// A Rust bool is a single byte, whereas a C# bool is marshalled as a 4 byte Win32 BOOL unless told
+/// otherwise.
+const BOOL_MARSHAL_AS: &str = "I1";
+
+/// The largest out buffer that is allocated on the stack, in bytes. Larger buffers are allocated as
+/// a managed array instead, as overflowing the stack kills the process without any chance to
+/// recover.
+const MAX_STACKALLOC_BYTES: usize = 1024;
+
+/// The ABI of every platform that bindings can be generated for.
+const TARGET_ABI: core::TargetAbi = core::TargetAbi::X86_64;
+
+/// An exported item which no C# could be generated for, eg a function taking a type with no C#
+/// equivalent.
+#[derive(Debug)]
+pub struct CodegenError {
+    /// The Rust name of the offending item
+    pub item: String,
+    pub reason: &'static str,
+}
+
+impl CodegenError {
+    fn for_item(item: &str) -> impl FnOnce(&'static str) -> Self + '_ {
+        move |reason| Self { item: item.to_string(), reason }
+    }
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Can't generate bindings for {}: {}", self.item, self.reason)
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
 /// A simple binding type requires no conversion to cross the FFI boundary
 #[derive(Clone, Debug)]
 struct SimpleBindingType {
@@ -31,6 +80,377 @@ struct ComplexBindingType {
     idiomatic_type: ast::CSharpType,
 }
 
+/// Options controlling the shape of the generated bindings
+#[derive(Clone, Debug, Default)]
+pub struct CodegenConfig {
+    /// Expose each `Option<&T>` argument through overloads taking either a `T` or nothing at all,
+    /// rather than as a single raw IntPtr argument.
+    pub optional_ref_overloads: bool,
+
+    /// Have wrappers take integer arguments narrower than 64 bits as an Int64/UInt64, narrowing
+    /// them back down before calling the native method.
+    pub widen_int_args: bool,
+
+    /// How widened integer arguments are narrowed back to their native width.
+    pub narrowing: OverflowCheck,
+
+    /// Have wrappers return unsigned integers narrower than 64 bits as the next wider signed type,
+    /// eg a `u32` as an Int64, so that callers can do arithmetic on them without casting.
+    ///
+    /// Unlike the narrowing of widened arguments, this conversion can never overflow, so it is
+    /// always unchecked.
+    pub widen_unsigned_returns: bool,
+
+    /// Alongside each wrapper taking `usize`/`isize` arguments (typically lengths) as a
+    /// UIntPtr/IntPtr, generate overloads taking them as an Int32 and as an Int64.
+    ///
+    /// The conversion is always checked, so a negative length throws an OverflowException rather
+    /// than wrapping around to a huge one.
+    pub length_overloads: bool,
+
+    /// Generate value based Equals/GetHashCode/==/!= members for each bound struct.
+    pub struct_equality: bool,
+
+    /// What to write in the comment at the top of the generated file.
+    pub header: FileHeader,
+
+    /// Expose functions following the Try pattern as a C# `bool TryFoo(.., out T value)`.
+    ///
+    /// A function follows the pattern if all of the following hold:
+    ///   - It returns a `bool`.
+    ///   - Its last argument is a `&mut T`.
+    ///   - None of its arguments were listed in `#[dotnet_bindgen(out(..))]`.
+    ///   - It wasn't marked with `#[dotnet_bindgen(no_try)]`.
+    ///
+    /// The trailing run of `&mut T` arguments then become `out` arguments, and the method name is
+    /// prefixed with `Try` if it isn't already.
+    pub try_pattern: bool,
+
+    /// Have each handle with a `#[dotnet_bindgen(release)]` function implement IAsyncDisposable,
+    /// with `DisposeAsync` running the release function on the thread pool.
+    pub async_dispose: bool,
+
+    /// Generate each item into a C# namespace mirroring the path of the Rust module it was defined
+    /// in, eg `my_lib::geometry` -> `MyLib.Geometry`, rather than all into one namespace.
+    pub module_namespaces: bool,
+
+    /// A namespace to prefix each of the module derived namespaces with, eg `Company.Product`.
+    pub namespace_root: Option<String>,
+
+    /// How string arguments are encoded, unless overridden with `#[dotnet_bindgen(charset(..))]`.
+    pub charset: CharSet,
+
+    /// Mark the wrappers of `#[must_use]` functions with a generated `[MustUse]` attribute, as well
+    /// as noting it in their documentation.
+    pub must_use_attribute: bool,
+
+    /// Group the extern methods of each class into a `#region Native Methods`, and their wrappers
+    /// into a `#region Public API`, rather than putting each wrapper after its extern method.
+    pub regions: bool,
+
+    /// Split each class of extern methods and their wrappers into separate `partial`
+    /// declarations, one holding the extern methods and one holding the wrappers.
+    pub partial_members: bool,
+
+    /// The oldest version of C# the generated source must compile with.
+    ///
+    /// `&T` arguments are passed as `in` parameters, avoiding a copy of large structs, but before
+    /// C# 7.2 they have to be `ref` parameters instead.
+    pub lang_version: LangVersion,
+
+    /// Have wrappers take `&T` arguments as `ref readonly` rather than `in` parameters, where the
+    /// language version allows it.
+    ///
+    /// Unlike with `in`, callers then can't pass a temporary which is silently copied to a hidden
+    /// local, so always pass a reference to their own variable.
+    pub ref_readonly: bool,
+
+    /// Mark wrappers which allocate a buffer on the stack with `[SkipLocalsInit]`, so that the
+    /// buffer isn't zeroed before the native function writes to it, where the language version
+    /// allows it.
+    ///
+    /// Only sound if every function taking an `out(..)` array writes every one of its elements,
+    /// as whatever was left on the stack is otherwise returned in their place.
+    pub skip_locals_init: bool,
+
+    /// Have wrappers encode UTF-8 string arguments themselves, into a stack buffer for short
+    /// strings or an array rented from the shared pool for long ones, rather than leaving it to
+    /// the runtime marshaller which allocates for every call. Needs C# 8.
+    pub utf8_string_buffers: bool,
+
+    /// The access modifiers of the generated extern methods and of the wrappers around them.
+    pub visibility: MethodVisibility,
+
+    /// The column to keep generated lines within, by wrapping long method signatures and
+    /// attribute argument lists with one item per line.
+    pub max_width: Option<usize>,
+
+    /// Mark each generated type with a `[GeneratedCode]` attribute crediting this tool, so that
+    /// analyzers and coverage tools can skip it.
+    pub generated_code: Option<GeneratedCodeTool>,
+}
+
+impl CodegenConfig {
+    /// The `[GeneratedCode]` attribute to mark each generated type with, if any.
+    fn generated_code_attribute(&self) -> Option<ast::Attribute> {
+        self.generated_code.as_ref().map(|tool| ast::Attribute {
+            name: "System.CodeDom.Compiler.GeneratedCode".to_string(),
+            positional_parameters: vec![
+                ast::LiteralValue::QuotedString(tool.name.clone()),
+                ast::LiteralValue::QuotedString(tool.version.clone()),
+            ],
+            named_parameters: Vec::new(),
+            condition: None,
+        })
+    }
+}
+
+/// The tool credited by the generated `[GeneratedCode]` attributes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GeneratedCodeTool {
+    name: String,
+    version: String,
+}
+
+impl GeneratedCodeTool {
+    pub fn new(name: &str, version: &str) -> Result<Self, &'static str> {
+        if name.trim().is_empty() {
+            return Err("The tool named by the GeneratedCode attribute can't be empty");
+        }
+        if version.trim().is_empty() {
+            return Err("The tool version given by the GeneratedCode attribute can't be empty");
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+impl Default for GeneratedCodeTool {
+    /// Credits this version of dotnet-bindgen itself
+    fn default() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// The access modifiers of the two kinds of generated method for each bound function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MethodVisibility {
+    /// The DllImport'd extern methods, which make up the raw P/Invoke surface.
+    pub externs: ast::Visibility,
+
+    /// The idiomatic wrappers forwarding to the extern methods.
+    ///
+    /// Other generated types call into these, so they can't be private.
+    pub wrappers: ast::Visibility,
+}
+
+impl Default for MethodVisibility {
+    fn default() -> Self {
+        Self {
+            externs: ast::Visibility::Private,
+            wrappers: ast::Visibility::Public,
+        }
+    }
+}
+
+/// The contents of the comment at the top of each generated file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileHeader {
+    /// Just a warning that the file is generated
+    Minimal,
+
+    /// Also names the version of this tool, and the name and version of each crate that the
+    /// bindings were generated from.
+    #[default]
+    Provenance,
+}
+
+impl std::str::FromStr for FileHeader {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(FileHeader::Minimal),
+            "provenance" => Ok(FileHeader::Provenance),
+            _ => Err("File header must be one of \"minimal\" or \"provenance\""),
+        }
+    }
+}
+
+/// How string arguments are encoded as they cross the boundary
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CharSet {
+    /// UTF-8, which is what `&str` arguments expect.
+    #[default]
+    Utf8,
+
+    /// The system's ANSI code page on Windows, and UTF-8 everywhere else. Only `&CStr` arguments
+    /// accept it, as `&str` ones must be UTF-8.
+    Ansi,
+
+    /// UTF-16, which is what `WideStr` arguments expect.
+    Unicode,
+}
+
+impl std::str::FromStr for CharSet {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(CharSet::Utf8),
+            "ansi" => Ok(CharSet::Ansi),
+            "unicode" => Ok(CharSet::Unicode),
+            _ => Err("Charset must be one of \"utf8\", \"ansi\", or \"unicode\""),
+        }
+    }
+}
+
+impl CharSet {
+    /// The UnmanagedType to marshal a string argument as, given the descriptor of its Rust type and
+    /// the charset it was explicitly given, if any.
+    ///
+    /// The encodings a string accepts are fixed by its Rust type, so a project wide default that
+    /// doesn't fit a string is ignored for it, whereas an explicit charset that doesn't fit is an
+    /// error.
+    fn marshal_as(
+        default: CharSet,
+        explicit: Option<CharSet>,
+        wide: bool,
+        utf8: bool,
+    ) -> Result<&'static str, &'static str> {
+        match (explicit, wide) {
+            (Some(CharSet::Unicode), false) => Err("Only WideStr arguments can have the unicode charset"),
+            (Some(CharSet::Utf8), true) | (Some(CharSet::Ansi), true) => {
+                Err("WideStr arguments can only have the unicode charset")
+            }
+            (_, true) => Ok("LPWStr"),
+            (Some(CharSet::Ansi), false) if utf8 => {
+                Err("&str arguments must be UTF-8, take a &CStr to accept ANSI strings")
+            }
+            (Some(CharSet::Ansi), false) => Ok("LPStr"),
+            (Some(_), false) => Ok("LPUTF8Str"),
+            (None, false) if default == CharSet::Ansi && !utf8 => Ok("LPStr"),
+            (None, false) => Ok("LPUTF8Str"),
+        }
+    }
+}
+
+/// A version of the C# language, which the generated source may use the syntax of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LangVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+impl LangVersion {
+    /// Newer than every version, so allows all syntax.
+    pub const LATEST: Self = Self { major: u8::MAX, minor: u8::MAX };
+
+    /// The first version with `in` parameters.
+    const IN_PARAMETERS: Self = Self { major: 7, minor: 2 };
+
+    /// The first version with `ref readonly` parameters.
+    const REF_READONLY_PARAMETERS: Self = Self { major: 12, minor: 0 };
+
+    /// The first version with the `[SkipLocalsInit]` attribute.
+    const SKIP_LOCALS_INIT: Self = Self { major: 9, minor: 0 };
+
+    /// The first version allowing a `stackalloc` to be passed as a `Span<T>` argument, and
+    /// disposing of ref structs with `using`.
+    const NESTED_STACKALLOC: Self = Self { major: 8, minor: 0 };
+}
+
+impl Default for LangVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+impl fmt::Display for LangVersion {
+    /// Formats the version as the value of a csproj `<LangVersion>` property.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::LATEST {
+            write!(f, "latest")
+        } else {
+            write!(f, "{}.{}", self.major, self.minor)
+        }
+    }
+}
+
+impl std::str::FromStr for LangVersion {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERR: &str = "Language version must be a number like \"7.3\" or \"12\", or \"latest\"";
+
+        if s == "latest" {
+            return Ok(Self::LATEST);
+        }
+
+        let (major, minor) = s.split_once('.').unwrap_or((s, "0"));
+        Ok(Self {
+            major: major.parse().map_err(|_| ERR)?,
+            minor: minor.parse().map_err(|_| ERR)?,
+        })
+    }
+}
+
+/// Whether a narrowing conversion throws on overflow, or silently truncates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowCheck {
+    #[default]
+    Checked,
+    Unchecked,
+}
+
+impl std::str::FromStr for OverflowCheck {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "checked" => Ok(OverflowCheck::Checked),
+            "unchecked" => Ok(OverflowCheck::Unchecked),
+            _ => Err("Overflow check must be one of \"checked\" or \"unchecked\""),
+        }
+    }
+}
+
+/// Whether a method with the given signature can only be declared in an unsafe context, as it
+/// takes or returns a raw pointer.
+fn signature_is_unsafe(args: &[ast::MethodArgument], return_ty: &ast::CSharpType) -> bool {
+    args.iter()
+        .map(|a| &a.ty)
+        .chain(std::iter::once(return_ty))
+        .any(|ty| matches!(ty, ast::CSharpType::Ptr { .. }))
+}
+
+/// Documents the cfg predicate an exported item was gated on, if any.
+///
+/// The generator only ever sees the items that were compiled in, but other builds of the same native
+/// library may not export them.
+fn cfg_doc(cfg: &Option<String>) -> Option<ast::DocComment> {
+    cfg.as_ref().map(|cfg| ast::DocComment {
+        remarks: vec![format!(
+            "Only exported by builds of the native library where cfg({}) holds.",
+            cfg
+        )],
+    })
+}
+
 /// Represents a type being passed between Rust/dotnet
 #[derive(Clone, Debug)]
 enum BindingType {
@@ -122,6 +542,23 @@ impl TryFrom<core::BindgenTypeDescriptor> for BindingType {
                 descriptor: Some(descriptor),
                 cs_type: CS::UInt64,
             }),
+            Desc::Float { width: 16 } => BindingType::Simple(SimpleBindingType {
+                descriptor: Some(descriptor),
+                cs_type: CS::Half,
+            }),
+            Desc::Float { width: 32 } => BindingType::Simple(SimpleBindingType {
+                descriptor: Some(descriptor),
+                cs_type: CS::Single,
+            }),
+            Desc::Float { width: 64 } => BindingType::Simple(SimpleBindingType {
+                descriptor: Some(descriptor),
+                cs_type: CS::Double,
+            }),
+            Desc::Float { .. } => return Err("Floats can only be 16, 32, or 64 bits wide"),
+            Desc::Size { signed } => BindingType::Simple(SimpleBindingType {
+                cs_type: if *signed { CS::intptr() } else { CS::uintptr() },
+                descriptor: Some(descriptor),
+            }),
             Desc::Slice { elem_type } => {
                 let elem_type = match BindingType::try_from(*elem_type.clone())? {
                     BindingType::Simple(s) => s.cs_type,
@@ -147,11 +584,83 @@ impl TryFrom<core::BindgenTypeDescriptor> for BindingType {
                     cs_type: CS::Struct { name }
                 })
             },
-            Desc::Bool => BindingType::Complex(ComplexBindingType {
-                descriptor,
-                thunk_type: CS::Byte,
-                idiomatic_type: CS::Bool,
+            // Needs an explicit MarshalAs wherever it appears by value, see BOOL_MARSHAL_AS
+            Desc::Bool => BindingType::Simple(SimpleBindingType {
+                descriptor: Some(descriptor),
+                cs_type: CS::Bool,
+            }),
+            Desc::Ptr { inner, .. } => match &**inner {
+                Desc::Opaque(o) => BindingType::Simple(SimpleBindingType {
+                    descriptor: Some(descriptor.clone()),
+                    cs_type: CS::Struct {
+                        name: ast::Ident::new(&o.name),
+                    },
+                }),
+                // Any other pointer is passed through untouched as the address it holds, whatever
+                // (or however many levels of pointer) it points to
+                _ => BindingType::Simple(SimpleBindingType {
+                    descriptor: Some(descriptor.clone()),
+                    cs_type: CS::intptr(),
+                }),
+            },
+            Desc::Ref { target, mutable: true } if matches!(**target, Desc::Array { .. }) => {
+                let elem_type = match &**target {
+                    Desc::Array { elem_type, .. } => match BindingType::try_from(*elem_type.clone())? {
+                        BindingType::Simple(s) => s.cs_type,
+                        BindingType::Complex(_) => {
+                            return Err("Can't generate code for arrays of non-trivial types yet")
+                        }
+                    },
+                    _ => unreachable!(),
+                };
+
+                BindingType::Complex(ComplexBindingType {
+                    descriptor,
+                    thunk_type: CS::Ptr {
+                        target: Box::new(elem_type.clone()),
+                    },
+                    idiomatic_type: CS::Array {
+                        elem_type: Box::new(elem_type),
+                    },
+                })
+            },
+            Desc::Str { .. } => BindingType::Simple(SimpleBindingType {
+                descriptor: Some(descriptor),
+                cs_type: CS::String,
+            }),
+            Desc::Array { .. } => {
+                return Err("Fixed size arrays can only be bound as out(..) `&mut [T; N]` arguments")
+            },
+            // There's nowhere to put the MarshalAs a `ref bool` would need
+            Desc::Ref { target, .. } if **target == Desc::Bool => {
+                return Err("Can't generate code for references to bools yet")
+            }
+            Desc::Ref { target, .. } => match BindingType::try_from(*target.clone())? {
+                BindingType::Simple(s) => BindingType::Simple(SimpleBindingType {
+                    descriptor: Some(descriptor),
+                    cs_type: s.cs_type,
+                }),
+                BindingType::Complex(_) => {
+                    return Err("Can't generate code for references to non-trivial types yet")
+                }
+            },
+            Desc::Enum(e) if e.c_like => BindingType::Simple(SimpleBindingType {
+                descriptor: Some(descriptor.clone()),
+                cs_type: CS::Struct {
+                    name: ast::Ident::new(&e.name),
+                },
             }),
+            Desc::Enum(_) => return Err("Can't pass enums whose variants carry data across the boundary"),
+            Desc::NullableRef { target } => {
+                if let BindingType::Complex(_) = BindingType::try_from(*target.clone())? {
+                    return Err("Can't generate code for optional references to non-trivial types yet")
+                }
+
+                BindingType::Simple(SimpleBindingType {
+                    descriptor: Some(descriptor),
+                    cs_type: CS::intptr(),
+                })
+            },
             _ => return Err("Unrecognized type"),
         };
 
@@ -164,12 +673,40 @@ struct BindingMethodArgument {
     ty: BindingType,
     rust_name: String,
     cs_name: String,
+    direction: ast::Direction,
+
+    /// How this string argument is encoded, if given explicitly.
+    charset: Option<CharSet>,
 }
 
 impl TryFrom<core::BindgenFunctionArgumentDescriptor> for BindingMethodArgument {
     type Error = &'static str;
 
     fn try_from(descriptor: core::BindgenFunctionArgumentDescriptor) -> Result<Self, Self::Error> {
+        let direction = match &descriptor.ty {
+            // The wrapper allocates these buffers itself, and passes a pointer to them
+            core::BindgenTypeDescriptor::Ref { target, .. }
+                if matches!(**target, core::BindgenTypeDescriptor::Array { .. }) =>
+            {
+                if !descriptor.out {
+                    return Err("Fixed size array arguments must be listed in out(..)");
+                }
+                ast::Direction::ByValue
+            }
+            core::BindgenTypeDescriptor::Ref { mutable: true, .. } if descriptor.out => ast::Direction::Out,
+            core::BindgenTypeDescriptor::Ref { mutable: true, .. } => ast::Direction::Ref,
+            core::BindgenTypeDescriptor::Ref { mutable: false, .. } => ast::Direction::In,
+            _ => ast::Direction::ByValue,
+        };
+
+        let charset = match &descriptor.charset {
+            Some(_) if !matches!(descriptor.ty, core::BindgenTypeDescriptor::Str { .. }) => {
+                return Err("Only string arguments can be given a charset");
+            }
+            Some(charset) => Some(charset.parse()?),
+            None => None,
+        };
+
         let ty = descriptor.ty.try_into()?;
         let rust_name = descriptor.name.to_string();
         let cs_name = descriptor.name.to_mixed_case();
@@ -177,12 +714,170 @@ impl TryFrom<core::BindgenFunctionArgumentDescriptor> for BindingMethodArgument
             ty,
             rust_name,
             cs_name,
+            direction,
+            charset,
         })
     }
 }
 
+/// The member of `OSPlatform` matching a platform given to `#[dotnet_bindgen(platforms(..))]`.
+fn os_platform(platform: &str) -> Option<&'static str> {
+    match platform {
+        "windows" => Some("Windows"),
+        "linux" => Some("Linux"),
+        "osx" => Some("OSX"),
+        _ => None,
+    }
+}
+
+/// The name of the opaque type, if this is a handle to one.
+fn handle_name(ty: &BindingType) -> Option<&str> {
+    match ty {
+        BindingType::Simple(SimpleBindingType {
+            descriptor: Some(core::BindgenTypeDescriptor::Ptr { inner, .. }),
+            ..
+        }) => match &**inner {
+            core::BindgenTypeDescriptor::Opaque(o) => Some(&o.name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 impl BindingMethodArgument {
-    fn transform_body_fragment(&self) -> ArgTransformBodyFragment {
+    /// Widens a narrow integer argument to the 64 bit type of the same signedness in the
+    /// idiomatic wrapper.
+    fn widened(self) -> Self {
+        use ast::CSharpType as CS;
+
+        let (descriptor, thunk_type, idiomatic_type) = match &self.ty {
+            BindingType::Simple(SimpleBindingType {
+                descriptor: Some(d @ core::BindgenTypeDescriptor::Int { width, signed }),
+                cs_type,
+            }) if *width < 64 => {
+                let wide = if *signed { CS::Int64 } else { CS::UInt64 };
+                (d.clone(), cs_type.clone(), wide)
+            }
+            _ => return self,
+        };
+
+        Self {
+            ty: BindingType::Complex(ComplexBindingType {
+                descriptor,
+                thunk_type,
+                idiomatic_type,
+            }),
+            ..self
+        }
+    }
+
+    /// Has the idiomatic wrapper take a pointer sized integer argument as the given integer type,
+    /// see [`CodegenConfig::length_overloads`].
+    fn with_length_type(self, length_type: &ast::CSharpType) -> Self {
+        let (descriptor, thunk_type) = match &self.ty {
+            BindingType::Simple(SimpleBindingType {
+                descriptor: Some(d @ core::BindgenTypeDescriptor::Size { .. }),
+                cs_type,
+            }) if self.direction == ast::Direction::ByValue => (d.clone(), cs_type.clone()),
+            _ => return self,
+        };
+
+        Self {
+            ty: BindingType::Complex(ComplexBindingType {
+                descriptor,
+                thunk_type,
+                idiomatic_type: length_type.clone(),
+            }),
+            ..self
+        }
+    }
+
+    /// Has the idiomatic wrapper encode a UTF-8 string argument into a `Utf8Buffer` itself, see
+    /// [`CodegenConfig::utf8_string_buffers`].
+    fn utf8_encoded(self, config: &CodegenConfig) -> Self {
+        let descriptor = match &self.ty {
+            BindingType::Simple(SimpleBindingType {
+                descriptor: Some(d @ core::BindgenTypeDescriptor::Str { .. }),
+                ..
+            }) if self.marshal_as(config) == Ok(Some("LPUTF8Str")) => d.clone(),
+            _ => return self,
+        };
+
+        Self {
+            ty: BindingType::Complex(ComplexBindingType {
+                descriptor,
+                thunk_type: ast::CSharpType::Ptr { target: Box::new(ast::CSharpType::Byte) },
+                idiomatic_type: ast::CSharpType::String,
+            }),
+            ..self
+        }
+    }
+
+    /// Whether the wrapper encodes this string argument into a `Utf8Buffer` itself.
+    fn is_utf8_encoded(&self) -> bool {
+        matches!(
+            &self.ty,
+            BindingType::Complex(ComplexBindingType { descriptor: core::BindgenTypeDescriptor::Str { .. }, .. })
+        )
+    }
+
+    /// The UnmanagedType the extern method should marshal this argument as, if it needs one.
+    fn marshal_as(&self, config: &CodegenConfig) -> Result<Option<&'static str>, &'static str> {
+        match &self.ty {
+            BindingType::Simple(SimpleBindingType {
+                descriptor: Some(core::BindgenTypeDescriptor::Str { wide, utf8 }),
+                ..
+            }) => CharSet::marshal_as(config.charset, self.charset, *wide, *utf8).map(Some),
+            BindingType::Simple(SimpleBindingType {
+                descriptor: Some(core::BindgenTypeDescriptor::Bool),
+                ..
+            }) => Ok(Some(BOOL_MARSHAL_AS)),
+            _ => Ok(None),
+        }
+    }
+
+    /// If this argument is an `Option<&T>`, the C# type of the T being referred to.
+    fn nullable_ref_target(&self) -> Option<ast::CSharpType> {
+        match &self.ty {
+            BindingType::Simple(SimpleBindingType {
+                descriptor: Some(core::BindgenTypeDescriptor::NullableRef { target }),
+                ..
+            }) => BindingType::try_from(*target.clone()).ok().map(|t| t.native_type()),
+            _ => None,
+        }
+    }
+
+    /// Passes the address of a `T` argument in place of an `Option<&T>`.
+    fn present_nullable_ref_fragment(&self) -> ArgTransformBodyFragment {
+        let elements = vec![
+            BodyElement::DeclareLocal {
+                id: AbstractIdent::Generated(0),
+                ty: ast::CSharpType::intptr(),
+            },
+            BodyElement::Unsafe,
+            BodyElement::Assignment {
+                lhs: Box::new(BodyElement::Ident(0.into())),
+                rhs: Box::new(BodyElement::Cast {
+                    ty: ast::CSharpType::intptr(),
+                    element: Box::new(BodyElement::AddressOf {
+                        element: Box::new(BodyElement::Ident(AbstractIdent::Explicit(
+                            self.cs_name.to_string(),
+                        ))),
+                    }),
+                }),
+            },
+        ];
+
+        ArgTransformBodyFragment {
+            elements,
+            output_ident: AbstractIdent::Generated(0),
+            direction: ast::Direction::ByValue,
+            result: None,
+        }
+    }
+
+    fn transform_body_fragment(&self, config: &CodegenConfig) -> ArgTransformBodyFragment {
+        let mut result = None;
         let (elements, output_ident) = match &self.ty {
             BindingType::Simple(_) => (
                 Vec::new(),
@@ -190,27 +885,54 @@ impl BindingMethodArgument {
             ),
             BindingType::Complex(complex_ty) => {
                 let elements = match &complex_ty.descriptor {
-                    core::BindgenTypeDescriptor::Bool => {
+                    core::BindgenTypeDescriptor::Int { .. } => vec![
+                        BodyElement::DeclareLocal {
+                            id: AbstractIdent::Generated(0),
+                            ty: complex_ty.thunk_type.clone(),
+                        },
+                        BodyElement::Assignment {
+                            lhs: Box::new(BodyElement::Ident(0.into())),
+                            rhs: Box::new(BodyElement::OverflowContext {
+                                checked: config.narrowing == OverflowCheck::Checked,
+                                element: Box::new(BodyElement::Cast {
+                                    ty: complex_ty.thunk_type.clone(),
+                                    element: Box::new(BodyElement::Ident(AbstractIdent::Explicit(
+                                        self.cs_name.to_string(),
+                                    ))),
+                                }),
+                            }),
+                        },
+                    ],
+                    core::BindgenTypeDescriptor::Size { signed } => {
                         let source_ident = Box::new(BodyElement::Ident(AbstractIdent::Explicit(
                             self.cs_name.to_string(),
                         )));
 
+                        // UIntPtr has no conversion from a signed integer, so go via a (checked)
+                        // UInt64 to reject negative values. Converting to either pointer type
+                        // throws by itself when the value is too large on a 32 bit platform.
+                        let value = match signed {
+                            true => source_ident,
+                            false => Box::new(BodyElement::OverflowContext {
+                                checked: true,
+                                element: Box::new(BodyElement::Cast {
+                                    ty: ast::CSharpType::UInt64,
+                                    element: source_ident,
+                                }),
+                            }),
+                        };
+
                         vec![
                             BodyElement::DeclareLocal {
                                 id: AbstractIdent::Generated(0),
-                                ty: ast::CSharpType::Byte,
+                                ty: complex_ty.thunk_type.clone(),
                             },
                             BodyElement::Assignment {
                                 lhs: Box::new(BodyElement::Ident(0.into())),
-                                rhs: Box::new(BodyElement::TernaryExpression {
-                                    test: source_ident.clone(),
-                                    true_branch: Box::new(
-                                        BodyElement::LiteralValue(LiteralValue::Number(1))
-                                    ),
-                                    false_branch: Box::new(
-                                        BodyElement::LiteralValue(LiteralValue::Number(0))
-                                    ),
-                                })
+                                rhs: Box::new(BodyElement::Cast {
+                                    ty: complex_ty.thunk_type.clone(),
+                                    element: value,
+                                }),
                             },
                         ]
                     },
@@ -255,7 +977,7 @@ impl BindingMethodArgument {
                             BodyElement::Unsafe,
                             BodyElement::FixedAssignment {
                                 ty: ast::CSharpType::Ptr {
-                                    target: Box::new((*elem_type.clone()).into()),
+                                    target: Box::new(*elem_type.clone()),
                                 },
                                 id: AbstractIdent::Generated(1),
                                 rhs: Box::new(BodyElement::AddressOf {
@@ -278,17 +1000,86 @@ impl BindingMethodArgument {
                         ]
                     }
 
-                    // Other descriptor types should fall under the Simple variant
-                    _ => unreachable!(),
-                };
+                    core::BindgenTypeDescriptor::Ref { target, .. } => {
+                        let (elem_ty, len) = match (&**target, &complex_ty.idiomatic_type) {
+                            (core::BindgenTypeDescriptor::Array { len, .. }, ast::CSharpType::Array { elem_type }) => {
+                                (*elem_type.clone(), *len)
+                            }
+                            _ => unreachable!(),
+                        };
 
-                (elements, AbstractIdent::Generated(0))
-            }
-        };
+                        let fits_on_stack = target
+                            .layout(TARGET_ABI)
+                            .is_some_and(|layout| layout.size <= MAX_STACKALLOC_BYTES);
 
-        ArgTransformBodyFragment {
+                        if fits_on_stack {
+                            // The buffer only lives as long as the wrapper, so copy it out to be returned
+                            result = Some(BodyElement::BufferToArray {
+                                buffer: 0.into(),
+                                elem_ty: elem_ty.clone(),
+                                len,
+                            });
+
+                            vec![
+                                BodyElement::Unsafe,
+                                BodyElement::StackAlloc {
+                                    id: 0.into(),
+                                    elem_ty,
+                                    len,
+                                },
+                            ]
+                        } else {
+                            // Fill a pinned managed array instead, which can then be returned as is
+                            result = Some(BodyElement::Ident(1.into()));
+
+                            vec![
+                                BodyElement::DeclareLocal {
+                                    id: 1.into(),
+                                    ty: complex_ty.idiomatic_type.clone(),
+                                },
+                                BodyElement::Assignment {
+                                    lhs: Box::new(BodyElement::Ident(1.into())),
+                                    rhs: Box::new(BodyElement::NewArray { elem_ty, len }),
+                                },
+                                BodyElement::Unsafe,
+                                BodyElement::FixedAssignment {
+                                    ty: complex_ty.thunk_type.clone(),
+                                    id: 0.into(),
+                                    rhs: Box::new(BodyElement::Ident(1.into())),
+                                },
+                            ]
+                        }
+                    }
+
+                    // Pinned for as long as the buffer it was encoded into is alive
+                    core::BindgenTypeDescriptor::Str { .. } => vec![
+                        BodyElement::Unsafe,
+                        BodyElement::Utf8Buffer {
+                            id: 1.into(),
+                            source: Box::new(BodyElement::Ident(AbstractIdent::Explicit(
+                                self.cs_name.to_string(),
+                            ))),
+                        },
+                        BodyElement::FixedAssignment {
+                            ty: complex_ty.thunk_type.clone(),
+                            id: 0.into(),
+                            rhs: Box::new(BodyElement::Ident(1.into())),
+                        },
+                    ],
+
+                    // Other descriptor types should fall under the Simple variant
+                    _ => unreachable!(),
+                };
+
+                (elements, AbstractIdent::Generated(0))
+            }
+        };
+
+        ArgTransformBodyFragment {
             elements,
             output_ident,
+            direction: self.direction,
+            result,
         }
     }
 }
@@ -339,24 +1130,6 @@ impl AbstractIdent {
     }
 }
 
-#[derive(Clone, Debug)]
-enum BinaryOperation {
-    NotEqual,
-}
-
-impl BinaryOperation {
-    fn sym(&self) -> &'static str {
-        match self {
-            BinaryOperation::NotEqual => "!=",
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-enum LiteralValue {
-    Number(i64),
-}
-
 /// An abstract part of a method body, roughly mapping 1-1 with an ast element.
 #[derive(Clone, Debug)]
 enum BodyElement {
@@ -369,7 +1142,7 @@ enum BodyElement {
     /// Just calls a method.
     MethodCall {
         method_name: String,
-        args: Vec<AbstractIdent>,
+        args: Vec<(ast::Direction, AbstractIdent)>,
     },
     /// A field/property of a variable, eg `foo.Length`.
     FieldAccess {
@@ -390,6 +1163,11 @@ enum BodyElement {
         ty: ast::CSharpType,
         element: Box<BodyElement>,
     },
+    /// Evaluates an element in an explicitly checked/unchecked overflow context
+    OverflowContext {
+        checked: bool,
+        element: Box<BodyElement>,
+    },
     Assignment {
         lhs: Box<BodyElement>,
         rhs: Box<BodyElement>,
@@ -405,19 +1183,31 @@ enum BodyElement {
     Return {
         element: Option<Box<BodyElement>>,
     },
-    /// A binary expression, eg `a != b`
-    BinaryExpression {
-        lhs: Box<BodyElement>,
-        rhs: Box<BodyElement>,
-        operation: BinaryOperation,
+    /// Declares a new local pointing to a buffer of `len` elements on the stack
+    StackAlloc {
+        id: AbstractIdent,
+        elem_ty: ast::CSharpType,
+        len: usize,
+    },
+    /// Allocates a new zeroed managed array of `len` elements
+    NewArray {
+        elem_ty: ast::CSharpType,
+        len: usize,
+    },
+    /// Copies a buffer of `len` elements into a new managed array
+    BufferToArray {
+        buffer: AbstractIdent,
+        elem_ty: ast::CSharpType,
+        len: usize,
     },
-    /// A ternary expression, eg `foo ? a : b`
-    TernaryExpression {
-        test: Box<BodyElement>,
-        true_branch: Box<BodyElement>,
-        false_branch: Box<BodyElement>,
+    /// A `//` comment on a line of its own, explaining the elements after it
+    Comment(String),
+    /// Encodes a string into a new `Utf8Buffer`, disposed of after the subsequent operations
+    /// inside its scope
+    Utf8Buffer {
+        id: AbstractIdent,
+        source: Box<BodyElement>,
     },
-    LiteralValue(LiteralValue),
 }
 
 impl BodyElement {
@@ -429,7 +1219,7 @@ impl BodyElement {
             BodyElement::MethodCall {
                 method_name: _,
                 args,
-            } => args.iter().filter_map(|a| a.generated_id()).max(),
+            } => args.iter().filter_map(|(_, a)| a.generated_id()).max(),
             BodyElement::FieldAccess {
                 element,
                 field_name: _,
@@ -437,26 +1227,31 @@ impl BodyElement {
             BodyElement::IndexAccess { element, index: _ } => element.max_abstract_id(),
             BodyElement::AddressOf { element } => element.max_abstract_id(),
             BodyElement::Cast { ty: _, element } => element.max_abstract_id(),
+            BodyElement::OverflowContext { checked: _, element } => element.max_abstract_id(),
             BodyElement::Assignment { lhs, rhs } => {
                 [lhs, rhs].iter().filter_map(|a| a.max_abstract_id()).max()
             }
             BodyElement::FixedAssignment { ty: _, id, rhs } => {
                 [id.generated_id(), rhs.max_abstract_id()]
                     .iter()
-                    .filter(|a| a.is_some())
-                    .map(|a| a.unwrap())
+                    .flatten()
+                    .copied()
                     .max()
             },
             BodyElement::Unsafe => None,
             BodyElement::Return { element: Some(element) } => element.max_abstract_id(),
             BodyElement::Return { element: None } => None,
-            BodyElement::BinaryExpression { lhs, rhs, operation: _ } => {
-                [lhs, rhs].iter().filter_map(|a| a.max_abstract_id()).max()
-            },
-            BodyElement::TernaryExpression { test, true_branch, false_branch } => {
-                [test, true_branch, false_branch].iter().filter_map(|a| a.max_abstract_id()).max()
+            BodyElement::StackAlloc { id, .. } => id.generated_id(),
+            BodyElement::NewArray { .. } => None,
+            BodyElement::BufferToArray { buffer, .. } => buffer.generated_id(),
+            BodyElement::Comment(_) => None,
+            BodyElement::Utf8Buffer { id, source } => {
+                [id.generated_id(), source.max_abstract_id()]
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .max()
             },
-            BodyElement::LiteralValue {..} => None,
         }
     }
 
@@ -468,7 +1263,7 @@ impl BodyElement {
                 method_name: _,
                 args,
             } => {
-                for arg in args.iter_mut() {
+                for (_, arg) in args.iter_mut() {
                     arg.apply_abstract_id_offset(offset);
                 }
             }
@@ -481,6 +1276,9 @@ impl BodyElement {
             }
             BodyElement::AddressOf { element } => element.apply_abstract_id_offset(offset),
             BodyElement::Cast { ty: _, element } => element.apply_abstract_id_offset(offset),
+            BodyElement::OverflowContext { checked: _, element } => {
+                element.apply_abstract_id_offset(offset)
+            }
             BodyElement::Assignment { lhs, rhs } => {
                 lhs.apply_abstract_id_offset(offset);
                 rhs.apply_abstract_id_offset(offset);
@@ -492,16 +1290,14 @@ impl BodyElement {
             BodyElement::Unsafe => (),
             BodyElement::Return { element: Some(element) } => element.apply_abstract_id_offset(offset),
             BodyElement::Return { element: None } => (),
-            BodyElement::BinaryExpression { lhs, rhs, operation: _ } => {
-                lhs.apply_abstract_id_offset(offset);
-                rhs.apply_abstract_id_offset(offset);
-            },
-            BodyElement::TernaryExpression { test, true_branch, false_branch } => {
-                test.apply_abstract_id_offset(offset);
-                true_branch.apply_abstract_id_offset(offset);
-                false_branch.apply_abstract_id_offset(offset);
+            BodyElement::StackAlloc { id, .. } => id.apply_abstract_id_offset(offset),
+            BodyElement::NewArray { .. } => (),
+            BodyElement::BufferToArray { buffer, .. } => buffer.apply_abstract_id_offset(offset),
+            BodyElement::Comment(_) => (),
+            BodyElement::Utf8Buffer { id, source } => {
+                id.apply_abstract_id_offset(offset);
+                source.apply_abstract_id_offset(offset);
             },
-            BodyElement::LiteralValue {..} => (),
         }
     }
 
@@ -514,13 +1310,16 @@ impl BodyElement {
             BodyElement::IndexAccess {..} => false,
             BodyElement::AddressOf {..} => false,
             BodyElement::Cast {..} => false,
+            BodyElement::OverflowContext {..} => false,
             BodyElement::Assignment {..} => false,
             BodyElement::FixedAssignment {..} => true,
             BodyElement::Unsafe => true,
             BodyElement::Return{..} => false,
-            BodyElement::BinaryExpression{..} => false,
-            BodyElement::LiteralValue {..} => false,
-            BodyElement::TernaryExpression {..} => false,
+            BodyElement::StackAlloc {..} => false,
+            BodyElement::NewArray {..} => false,
+            BodyElement::BufferToArray {..} => false,
+            BodyElement::Comment(_) => false,
+            BodyElement::Utf8Buffer {..} => true,
         }
     }
 
@@ -533,13 +1332,16 @@ impl BodyElement {
             BodyElement::IndexAccess {..} => false,
             BodyElement::AddressOf {..} => false,
             BodyElement::Cast {..} => false,
+            BodyElement::OverflowContext {..} => false,
             BodyElement::Assignment {..} => false,
             BodyElement::FixedAssignment {..} => true,
             BodyElement::Unsafe => true,
             BodyElement::Return{..} => true,
-            BodyElement::BinaryExpression{..} => false,
-            BodyElement::LiteralValue {..} => false,
-            BodyElement::TernaryExpression {..} => false,
+            BodyElement::StackAlloc {..} => true,
+            BodyElement::NewArray {..} => false,
+            BodyElement::BufferToArray {..} => false,
+            BodyElement::Comment(_) => true,
+            BodyElement::Utf8Buffer {..} => true,
         }
     }
 
@@ -554,7 +1356,7 @@ impl BodyElement {
             ),
             BodyElement::MethodCall { method_name, args } => {
                 let args = args.iter()
-                    .map(|a| a.to_concrete_ident())
+                    .map(|(direction, a)| (*direction, a.to_concrete_ident()))
                     .collect();
                 Box::new(
                     ast::MethodInvocation {
@@ -587,6 +1389,12 @@ impl BodyElement {
                     element: element.to_ast_node(),
                 }
             ),
+            BodyElement::OverflowContext { checked, element } => Box::new(
+                ast::OverflowContext {
+                    checked: *checked,
+                    element: element.to_ast_node(),
+                }
+            ),
             BodyElement::Assignment { lhs, rhs } => Box::new(
                 ast::BinaryExpression {
                     lhs: lhs.to_ast_node(),
@@ -604,6 +1412,20 @@ impl BodyElement {
             BodyElement::Unsafe => Box::new(
                 ast::UnsafeStatement {}
             ),
+            BodyElement::Utf8Buffer { id, source } => {
+                let ty = ast::CSharpType::Struct { name: "Utf8Buffer".into() };
+                Box::new(ast::UsingResource {
+                    ty: ty.clone(),
+                    id: id.to_concrete_ident(),
+                    rhs: Box::new(ast::ObjectCreation {
+                        ty,
+                        args: vec![
+                            source.to_ast_node(),
+                            Box::new(ast::Ident::new("stackalloc Byte[Utf8Buffer.StackLength]")),
+                        ],
+                    }),
+                })
+            },
             BodyElement::Return { element } => {
                 Box::new(ast::ReturnStatement {
                     value: match element {
@@ -612,25 +1434,33 @@ impl BodyElement {
                     }
                 })
             },
-            BodyElement::BinaryExpression { lhs, rhs, operation } => Box::new(
-                ast::BinaryExpression {
-                    lhs: lhs.to_ast_node(),
-                    rhs: rhs.to_ast_node(),
-                    operation_sym: operation.sym(),
+            BodyElement::StackAlloc { id, elem_ty, len } => Box::new(
+                ast::StackAllocDeclaration {
+                    name: id.to_concrete_ident(),
+                    elem_ty: elem_ty.clone(),
+                    len: *len,
                 }
             ),
-            BodyElement::LiteralValue(val) => Box::new(
-                match val {
-                    LiteralValue::Number(num) => ast::LiteralValue::Number(*num),
+            BodyElement::NewArray { elem_ty, len } => Box::new(
+                ast::Ident(format!("new {}[{}]", elem_ty, len))
+            ),
+            BodyElement::BufferToArray { buffer, elem_ty, len } => Box::new(
+                ast::MethodInvocation {
+                    target: Some(ast::Ident(format!(
+                        "new Span<{}>({}, {})",
+                        elem_ty,
+                        buffer.to_concrete_ident(),
+                        len,
+                    ))),
+                    method_name: "ToArray".into(),
+                    args: Vec::new(),
                 }
             ),
-            BodyElement::TernaryExpression { test, true_branch, false_branch } => Box::new(
-                ast::TernaryExpression {
-                    test: test.to_ast_node(),
-                    true_branch: true_branch.to_ast_node(),
-                    false_branch: false_branch.to_ast_node(),
+            BodyElement::Comment(text) => Box::new(
+                ast::RawLines {
+                    lines: vec![format!("// {}", text)],
                 }
-            )
+            ),
         }
     }
 }
@@ -643,13 +1473,22 @@ impl BodyElement {
 /// #let arg_name = "foo".to_string();
 /// let frag = ArgTransformBodyElement {
 ///     elements: Vec::new(),
-///     output_ident: AbstractIdent::Explicit(arg_name)
+///     output_ident: AbstractIdent::Explicit(arg_name),
+///     direction: ast::Direction::ByValue,
+///     result: None,
 /// };
 /// ```
 #[derive(Clone, Debug)]
 struct ArgTransformBodyFragment {
     elements: Vec<BodyElement>,
     output_ident: AbstractIdent,
+
+    /// How the output ident is passed to the bound method
+    direction: ast::Direction,
+
+    /// A value to return from the wrapper once the bound method has been called, in which case the
+    /// argument is omitted from the wrapper's signature.
+    result: Option<BodyElement>,
 }
 
 impl ArgTransformBodyFragment {
@@ -657,19 +1496,19 @@ impl ArgTransformBodyFragment {
         let max = self
             .elements
             .iter()
+            .chain(&self.result)
             .filter_map(|e| e.max_abstract_id())
             .max();
 
-        match self.output_ident.generated_id() {
-            Some(id) => assert!(id <= max.unwrap()),
-            None => (),
+        if let Some(id) = self.output_ident.generated_id() {
+            assert!(id <= max.unwrap());
         }
 
         max
     }
 
     fn apply_abstract_id_offset(&mut self, offset: u32) {
-        for el in self.elements.iter_mut() {
+        for el in self.elements.iter_mut().chain(&mut self.result) {
             el.apply_abstract_id_offset(offset);
         }
 
@@ -683,34 +1522,37 @@ struct BindingMethodBody {
 }
 
 impl BindingMethodBody {
+    /// Whether the body allocates a buffer on the stack, which is zeroed unless told otherwise.
+    fn allocates_on_stack(&self) -> bool {
+        self.body_elements.iter().any(|el| matches!(el, BodyElement::StackAlloc { .. }))
+    }
+
     pub fn new(
         descriptor: &core::BindgenFunctionDescriptor,
-        args: &[BindingMethodArgument]
+        mut transform_fragments: Vec<ArgTransformBodyFragment>,
+        config: &CodegenConfig,
     ) -> Self {
-        let mut transform_fragments: Vec<_> =
-            args.iter().map(|a| a.transform_body_fragment()).collect();
-
         // Ensure that their generated idents from each fragment don't intersect
         let mut offset = 0;
         for frag in transform_fragments.iter_mut() {
-            match frag.max_abstract_id() {
-                Some(m) => {
-                    frag.apply_abstract_id_offset(offset);
-                    offset += m + 1;
-                }
-                None => ()
+            if let Some(m) = frag.max_abstract_id() {
+                frag.apply_abstract_id_offset(offset);
+                offset += m + 1;
             }
         }
 
+        // An unsafe block already covers everything after it, so any later ones would be redundant
+        let mut seen_unsafe = false;
         let mut body_elements: Vec<_> = transform_fragments
             .iter()
             .flat_map(|frag| frag.elements.iter().cloned())
+            .filter(|el| !matches!(el, BodyElement::Unsafe) || !std::mem::replace(&mut seen_unsafe, true))
             .collect();
 
         // Add one final body element, calling the bound method with all of the (possibly) transformed arguments.
-        let invocation_args: Vec<AbstractIdent> = transform_fragments
+        let invocation_args: Vec<_> = transform_fragments
             .iter()
-            .map(|frag| frag.output_ident.clone())
+            .map(|frag| (frag.direction, frag.output_ident.clone()))
             .collect();
 
         let underlying_call = BodyElement::MethodCall {
@@ -718,12 +1560,51 @@ impl BindingMethodBody {
             args: invocation_args,
         };
 
-        if descriptor.return_ty != core::BindgenTypeDescriptor::Void {
+        // Validated by BindingMethod::new to only be possible for functions returning nothing
+        if let Some(result) = transform_fragments.iter().find_map(|frag| frag.result.clone()) {
+            body_elements.push(underlying_call);
             body_elements.push(BodyElement::Return {
-                element: Some(Box::new(underlying_call))
+                element: Some(Box::new(result)),
             });
-        } else {
-            body_elements.push(underlying_call);
+
+            return Self { body_elements };
+        }
+
+        let converted_return = wrapper_return_ty(descriptor, config);
+
+        match &descriptor.return_ty {
+            core::BindgenTypeDescriptor::Void => body_elements.push(underlying_call),
+
+            // Validated by BindingMethod::new to be an i32 with the same representation as the enum
+            _ if descriptor.error_enum.is_some() => body_elements.push(BodyElement::Return {
+                element: Some(Box::new(BodyElement::Cast {
+                    ty: converted_return.unwrap(),
+                    element: Box::new(underlying_call),
+                })),
+            }),
+
+            // Every value of the native type fits in the wider one, so the conversion can skip the
+            // overflow check that narrowing conversions need.
+            core::BindgenTypeDescriptor::Int { width, .. } if converted_return.is_some() => {
+                let wide = converted_return.unwrap();
+                body_elements.push(BodyElement::Comment(format!(
+                    "Lossless: every {}-bit unsigned value fits in an {}",
+                    width, wide,
+                )));
+                body_elements.push(BodyElement::Return {
+                    element: Some(Box::new(BodyElement::OverflowContext {
+                        checked: false,
+                        element: Box::new(BodyElement::Cast {
+                            ty: wide,
+                            element: Box::new(underlying_call),
+                        }),
+                    })),
+                });
+            }
+
+            _ => body_elements.push(BodyElement::Return {
+                element: Some(Box::new(underlying_call))
+            }),
         }
 
         Self { body_elements }
@@ -762,6 +1643,165 @@ impl BindingMethodBody {
     }
 }
 
+/// The signed C# type that an unsigned integer narrower than 64 bits always fits in, see
+/// [`CodegenConfig::widen_unsigned_returns`].
+fn lossless_signed_widening(descriptor: &core::BindgenTypeDescriptor) -> Option<ast::CSharpType> {
+    match descriptor {
+        core::BindgenTypeDescriptor::Int { width: 8, signed: false } => Some(ast::CSharpType::Int16),
+        core::BindgenTypeDescriptor::Int { width: 16, signed: false } => Some(ast::CSharpType::Int32),
+        core::BindgenTypeDescriptor::Int { width: 32, signed: false } => Some(ast::CSharpType::Int64),
+        _ => None,
+    }
+}
+
+/// The type the wrappers of the described function return in place of its own, if it's converted
+/// to an error enum or widened.
+fn wrapper_return_ty(descriptor: &core::BindgenFunctionDescriptor, config: &CodegenConfig) -> Option<ast::CSharpType> {
+    match &descriptor.error_enum {
+        Some(error_enum) => Some(ast::CSharpType::Struct { name: ast::Ident::new(error_enum) }),
+        None => lossless_signed_widening(&descriptor.return_ty).filter(|_| config.widen_unsigned_returns),
+    }
+}
+
+/// Whether the type, or any type it refers to or contains, matches the predicate.
+fn mentions_type(ty: &core::BindgenTypeDescriptor, pred: &dyn Fn(&core::BindgenTypeDescriptor) -> bool) -> bool {
+    use dotnet_bindgen_core::BindgenTypeDescriptor as Desc;
+
+    pred(ty) || match ty {
+        Desc::Slice { elem_type } | Desc::Array { elem_type, .. } => mentions_type(elem_type, pred),
+        Desc::Ref { target, .. } | Desc::NullableRef { target } => mentions_type(target, pred),
+        Desc::Ptr { inner, .. } => mentions_type(inner, pred),
+        Desc::Struct(s) => s.fields.iter().any(|f| mentions_type(&f.ty, pred)),
+        _ => false,
+    }
+}
+
+/// Whether the type holds a slice or array of structs with string buffer fields, which the
+/// wrappers can't pin or stackalloc as those structs aren't blittable.
+fn buffers_string_structs(ty: &core::BindgenTypeDescriptor) -> bool {
+    use dotnet_bindgen_core::BindgenTypeDescriptor as Desc;
+
+    let is_string_struct = |t: &Desc| matches!(t, Desc::Struct(s) if s.fields.iter().any(|f| f.string_buffer));
+    mentions_type(ty, &|t| match t {
+        Desc::Slice { elem_type } | Desc::Array { elem_type, .. } => mentions_type(elem_type, &is_string_struct),
+        _ => false,
+    })
+}
+
+/// A single idiomatic C# method, which forwards to the DllImport'd thunk.
+#[derive(Clone, Debug)]
+struct CsThunk {
+    /// The name, type, and direction of each argument this method takes
+    args: Vec<(String, ast::CSharpType, ast::Direction)>,
+
+    /// The type this method returns, if it differs from that of the bound function.
+    return_ty: Option<ast::CSharpType>,
+
+    body: BindingMethodBody,
+}
+
+impl CsThunk {
+    /// The thunk exposing every argument with its idiomatic type.
+    fn new(
+        descriptor: &core::BindgenFunctionDescriptor,
+        args: &[BindingMethodArgument],
+        config: &CodegenConfig,
+    ) -> Self {
+        let fragments: Vec<_> = args.iter().map(|a| a.transform_body_fragment(config)).collect();
+
+        let mut thunk_args = Vec::new();
+        let mut return_ty = wrapper_return_ty(descriptor, config);
+        for (arg, frag) in args.iter().zip(&fragments) {
+            match frag.result {
+                Some(_) => return_ty = Some(arg.ty.idiomatic_type()),
+                None => thunk_args.push((arg.cs_name.to_string(), arg.ty.idiomatic_type(), arg.direction)),
+            }
+        }
+
+        Self {
+            args: thunk_args,
+            return_ty,
+            body: BindingMethodBody::new(descriptor, fragments, config),
+        }
+    }
+
+    /// The set of overloads that replace each `Option<&T>` argument with either a plain `T`, or
+    /// with nothing at all (passing null to the native method).
+    ///
+    /// Overloads are generated for every combination of optional arguments being present or
+    /// omitted. Where two combinations would produce identical C# signatures, the one omitting the
+    /// later arguments wins, mirroring how C# optional parameters behave.
+    fn optional_ref_overloads(
+        descriptor: &core::BindgenFunctionDescriptor,
+        args: &[BindingMethodArgument],
+        config: &CodegenConfig,
+    ) -> Vec<Self> {
+        let optional_args: Vec<_> = args
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.nullable_ref_target().is_some())
+            .map(|(idx, _)| idx)
+            .collect();
+
+        // Each bit of the mask represents one optional argument, set if it is omitted.
+        let mut masks: Vec<u32> = (0..(1u32 << optional_args.len())).collect();
+        masks.sort_by_key(|m| (m.count_ones(), std::cmp::Reverse(*m)));
+
+        let mut seen_signatures = std::collections::HashSet::new();
+        let mut overloads = Vec::new();
+        for mask in masks {
+            let is_omitted = |arg_idx: usize| {
+                optional_args
+                    .iter()
+                    .position(|&a| a == arg_idx)
+                    .is_some_and(|bit| mask & (1 << bit) != 0)
+            };
+
+            let mut thunk_args = Vec::new();
+            let mut return_ty = wrapper_return_ty(descriptor, config);
+            let mut fragments = Vec::new();
+            for (idx, arg) in args.iter().enumerate() {
+                match arg.nullable_ref_target() {
+                    Some(_) if is_omitted(idx) => {
+                        fragments.push(ArgTransformBodyFragment {
+                            elements: Vec::new(),
+                            output_ident: "IntPtr.Zero".into(),
+                            direction: ast::Direction::ByValue,
+                            result: None,
+                        });
+                    }
+                    Some(target) => {
+                        thunk_args.push((arg.cs_name.to_string(), target, ast::Direction::ByValue));
+                        fragments.push(arg.present_nullable_ref_fragment());
+                    }
+                    None => {
+                        let frag = arg.transform_body_fragment(config);
+                        match frag.result {
+                            Some(_) => return_ty = Some(arg.ty.idiomatic_type()),
+                            None => thunk_args.push((arg.cs_name.to_string(), arg.ty.idiomatic_type(), arg.direction)),
+                        }
+                        fragments.push(frag);
+                    }
+                }
+            }
+
+            let signature: Vec<_> = thunk_args
+                .iter()
+                .map(|(_, ty, direction)| (ty.to_string(), *direction))
+                .collect();
+            if seen_signatures.insert(signature) {
+                overloads.push(Self {
+                    args: thunk_args,
+                    return_ty,
+                    body: BindingMethodBody::new(descriptor, fragments, config),
+                });
+            }
+        }
+
+        overloads
+    }
+}
+
 #[derive(Clone, Debug)]
 struct BindingMethod {
     args: Vec<BindingMethodArgument>,
@@ -775,6 +1815,7 @@ struct BindingMethod {
     /// 
     /// This isn't neccesarily unique among the bindings, or the name of the symbol in the binary,
     /// as the if a thunk is generated the method doens't have to have #[no_mangle] attached.
+    #[allow(dead_code)]
     rust_name: String,
 
     /// The symbol name of the generated rust thunk, if one was generated.
@@ -787,27 +1828,187 @@ struct BindingMethod {
     /// Typically just rust_name.to_camel_case().
     cs_name: String,
 
-    /// If a C# thunk must be generated, the body of that thunk.
-    cs_thunk_body: Option<BindingMethodBody>,
+    /// The static class the method is generated into, `TopLevelMethods` unless given explicitly.
+    class_name: String,
+
+    /// The idiomatic C# methods to generate, each forwarding to the DllImport'd thunk.
+    ///
+    /// Usually exactly one, but may be several overloads of the same name.
+    cs_thunks: Vec<CsThunk>,
+
+    /// The cfg predicate that the bound function was gated on, if any.
+    cfg: Option<String>,
+
+    /// The operating systems the bound function is supported on, empty if unrestricted.
+    platforms: Vec<String>,
+
+    /// C# source to use as the body of the wrapper, in place of the generated one.
+    custom_body: Option<String>,
+
+    /// Whether the bound function frees the single handle it takes.
+    release: bool,
+
+    /// The UnmanagedType the extern method marshals each argument as, if it needs one.
+    arg_marshalling: Vec<Option<String>>,
+
+    /// Whether the bound function was selected for the benchmark harness.
+    bench: bool,
+
+    /// The C# expressions to pass through the bound function in the round trip test, if it was
+    /// marked as returning its argument unchanged.
+    roundtrip_values: Option<Vec<String>>,
+
+    /// The reason the return value shouldn't be ignored, if the bound function is `#[must_use]`.
+    must_use: Option<String>,
+
+    /// Whether to mark the wrapper of a `#[must_use]` function with a `[MustUse]` attribute.
+    must_use_attribute: bool,
+
+    /// Whether the bound function never returns, so its wrapper gets a `[DoesNotReturn]` attribute.
+    diverges: bool,
+
+    /// The access modifiers of the extern method and its wrappers.
+    visibility: MethodVisibility,
+
+    /// Whether wrappers allocating a buffer on the stack should skip zeroing it.
+    skip_locals_init: bool,
 }
 
 impl BindingMethod {
-    pub fn new(binary_name: &str, descriptor: &core::BindgenFunctionDescriptor) -> Result<Self, &'static str> {
-        let binary_name = binary_name.to_string();
+    pub fn new(
+        binary_name: &str,
+        descriptor: &core::BindgenFunctionDescriptor,
+        config: &CodegenConfig,
+    ) -> Result<Self, &'static str> {
+        // Functions imported by an extern block live in the library they were linked from
+        let binary_name = descriptor.library.as_deref().unwrap_or(binary_name).to_string();
+
+        // A custom body is written against the plain signature, and calls the extern with its
+        // arguments as they are
+        let plain_args = descriptor.body.is_some();
 
-        let args = descriptor
+        let mut args = descriptor
             .arguments
             .iter()
             .map(|arg_desc| BindingMethodArgument::try_from(arg_desc.clone()))
+            .map(|arg| match arg {
+                Ok(arg) if config.widen_int_args && !plain_args => Ok(arg.widened()),
+                arg => arg,
+            })
+            .map(|arg| match arg {
+                Ok(arg) if config.utf8_string_buffers
+                    && config.lang_version >= LangVersion::NESTED_STACKALLOC
+                    && !plain_args => {
+                    Ok(arg.utf8_encoded(config))
+                }
+                arg => arg,
+            })
             .collect::<Result<Vec<_>, _>>()?;
 
         let return_ty = descriptor.return_ty.clone().try_into()?;
 
+        if let core::BindgenTypeDescriptor::Str { .. } = descriptor.return_ty {
+            return Err("Can't generate code for functions returning strings yet");
+        }
+
+        let arg_marshalling = args
+            .iter()
+            .map(|a| a.marshal_as(config).map(|m| m.map(|m| m.to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if descriptor.error_enum.is_some()
+            && descriptor.return_ty != (core::BindgenTypeDescriptor::Int { width: 32, signed: true })
+        {
+            return Err("The error_enum option can only be given to functions returning an i32");
+        }
+
+        if descriptor.release && !(args.len() == 1 && handle_name(&args[0].ty).is_some()) {
+            return Err("Release functions must take a single handle argument");
+        }
+
+        // The descriptor may come from a binary built by a different version of the macro
+        if descriptor.platforms.iter().any(|p| os_platform(p).is_none()) {
+            return Err("Platforms must be one of \"windows\", \"linux\", or \"osx\"");
+        }
+
+        if descriptor.arguments.iter().any(|a| buffers_string_structs(&a.ty))
+            || buffers_string_structs(&descriptor.return_ty)
+        {
+            return Err("Structs with string buffer fields aren't blittable, so can't be passed in \
+                slices or arrays");
+        }
+
+        let out_buffers = descriptor.arguments
+            .iter()
+            .filter(|a| matches!(
+                &a.ty,
+                core::BindgenTypeDescriptor::Ref { target, .. }
+                    if matches!(**target, core::BindgenTypeDescriptor::Array { .. })
+            ))
+            .count();
+        if out_buffers > 1 || (out_buffers == 1 && descriptor.return_ty != core::BindgenTypeDescriptor::Void) {
+            return Err("Fixed size out buffers are returned by the wrapper, so a function may only \
+                have one, and must not return anything itself");
+        }
+
+        let roundtrip_values = match descriptor.roundtrip {
+            true => Some(Self::roundtrip_values(descriptor)?),
+            false => None,
+        };
+
         let rust_name = descriptor.real_name.to_string();
         let rust_thunk_name = descriptor.thunk_name.to_string();
-        let cs_name = rust_name.to_camel_case();
+        let mut cs_name = rust_name.to_camel_case();
+
+        for arg in args.iter_mut().filter(|a| a.direction == ast::Direction::In) {
+            if config.lang_version < LangVersion::IN_PARAMETERS {
+                arg.direction = ast::Direction::Ref;
+            } else if config.ref_readonly && config.lang_version >= LangVersion::REF_READONLY_PARAMETERS {
+                arg.direction = ast::Direction::RefReadonly;
+            }
+        }
+
+        if config.try_pattern && Self::matches_try_pattern(descriptor) {
+            for arg in args.iter_mut().rev().take_while(|a| a.direction == ast::Direction::Ref) {
+                arg.direction = ast::Direction::Out;
+            }
 
-        let cs_thunk_body = Some(BindingMethodBody::new(descriptor, &args));
+            if !cs_name.starts_with("Try") {
+                cs_name = format!("Try{}", cs_name);
+            }
+        }
+
+        // A custom body is written against one specific signature, so can't be used for overloads
+        let thunks_for = |args: &[BindingMethodArgument]| {
+            if config.optional_ref_overloads && descriptor.body.is_none() {
+                CsThunk::optional_ref_overloads(descriptor, args, config)
+            } else {
+                vec![CsThunk::new(descriptor, args, config)]
+            }
+        };
+
+        let mut cs_thunks = thunks_for(&args);
+
+        let has_length_args = args.iter().any(|a| {
+            a.direction == ast::Direction::ByValue
+                && matches!(a.ty, BindingType::Simple(SimpleBindingType {
+                    descriptor: Some(core::BindgenTypeDescriptor::Size { .. }),
+                    ..
+                }))
+        });
+        if config.length_overloads && has_length_args && descriptor.body.is_none() {
+            for length_type in &[ast::CSharpType::Int32, ast::CSharpType::Int64] {
+                let length_args: Vec<_> = args
+                    .iter()
+                    .map(|a| a.clone().with_length_type(length_type))
+                    .collect();
+                cs_thunks.extend(thunks_for(&length_args));
+            }
+        }
+
+        let cfg = descriptor.cfg.clone();
+        let platforms = descriptor.platforms.clone();
+        let custom_body = descriptor.body.clone();
 
         Ok(Self {
             binary_name,
@@ -816,42 +2017,158 @@ impl BindingMethod {
             rust_name,
             rust_thunk_name,
             cs_name,
-            cs_thunk_body,
+            class_name: descriptor.class.clone().unwrap_or_else(|| DEFAULT_CLASS_NAME.to_string()),
+            cs_thunks,
+            cfg,
+            platforms,
+            custom_body,
+            release: descriptor.release,
+            arg_marshalling,
+            bench: descriptor.bench,
+            roundtrip_values,
+            // Nothing is returned to be used from a function returning nothing
+            must_use: descriptor.must_use
+                .clone()
+                .filter(|_| descriptor.return_ty != core::BindgenTypeDescriptor::Void),
+            must_use_attribute: config.must_use_attribute,
+            diverges: descriptor.diverges,
+            visibility: config.visibility,
+            skip_locals_init: config.skip_locals_init && config.lang_version >= LangVersion::SKIP_LOCALS_INIT,
         })
     }
 
+    /// Whether any of the wrappers encode a string argument into a `Utf8Buffer`.
+    fn uses_utf8_buffer(&self) -> bool {
+        self.args.iter().any(|a| a.is_utf8_encoded())
+    }
+
+    /// The name of the handle this method frees, if it is a release function.
+    fn released_handle(&self) -> Option<&str> {
+        if self.release {
+            self.args.first().and_then(|a| handle_name(&a.ty))
+        } else {
+            None
+        }
+    }
+
     /// Generate the ast nodes for this bound method
     /// 
     /// This may be more than one method, eg if a thunk is needed to marshall arguments/return values to/from
     /// an FFI stable representation.
     pub fn to_ast_methods(&self) -> Vec<ast::Method> {
-        vec![
-            self.dll_imported_method(),
-            self.thunk_method(),
-        ]
+        std::iter::once(self.dll_imported_method())
+            .chain(self.wrapper_methods())
+            .collect()
+    }
+
+    /// Just the idiomatic wrappers of this bound method, without the extern method they call.
+    fn wrapper_methods(&self) -> Vec<ast::Method> {
+        self.cs_thunks.iter().map(|t| self.thunk_method(t)).collect()
+    }
+
+    /// Whether the bound function looks like a Rust version of the C# `bool TryFoo(.., out T value)`
+    /// idiom, see [`CodegenConfig::try_pattern`].
+    fn matches_try_pattern(descriptor: &core::BindgenFunctionDescriptor) -> bool {
+        let is_mut_ref = |a: &&core::BindgenFunctionArgumentDescriptor| {
+            matches!(a.ty, core::BindgenTypeDescriptor::Ref { mutable: true, .. })
+        };
+
+        descriptor.return_ty == core::BindgenTypeDescriptor::Bool
+            && !descriptor.no_try
+            && descriptor.arguments.last().is_some_and(|a| is_mut_ref(&a))
+            && !descriptor.arguments.iter().any(|a| a.out)
+    }
+
+    /// Does this method take a handle as its first argument, and return a handle of the same type?
+    ///
+    /// Such methods are exposed as extension methods on the handle type, so that builder style APIs
+    /// can be chained fluently.
+    fn is_builder(&self) -> bool {
+        match (self.args.first(), handle_name(&self.return_ty)) {
+            (Some(first), Some(ret)) => handle_name(&first.ty) == Some(ret),
+            _ => false,
+        }
+    }
+
+    /// Throws a PlatformNotSupportedException when called on an OS the function doesn't support,
+    /// rather than failing with a confusing error from the native library loader.
+    fn platform_guard(&self) -> Option<Box<dyn ast::AstNode>> {
+        let checks = self.platforms.iter().map(|platform| {
+            let os_platform = os_platform(platform).expect("Platforms are checked when the method is created");
+
+            Box::new(ast::MethodInvocation {
+                target: Some("RuntimeInformation".into()),
+                method_name: "IsOSPlatform".into(),
+                args: vec![(
+                    ast::Direction::ByValue,
+                    ast::Ident(format!("OSPlatform.{}", os_platform)),
+                )],
+            }) as Box<dyn ast::AstNode>
+        });
+
+        let condition = checks.reduce(|lhs, rhs| {
+            Box::new(ast::BinaryExpression {
+                lhs,
+                rhs,
+                operation_sym: "||",
+            })
+        })?;
+
+        Some(Box::new(ast::IfStatement {
+            condition: Box::new(ast::Negation { element: condition }),
+            body: vec![Box::new(ast::ThrowStatement {
+                exception: "PlatformNotSupportedException".into(),
+                message: format!(
+                    "{} is only supported on: {}",
+                    self.cs_name,
+                    self.platforms.join(", ")
+                ),
+            })],
+        }))
     }
 
     fn dll_imported_method(&self) -> ast::Method {
-        let attributes = vec![
+        let mut attributes = vec![
             ast::Attribute::dll_import(&self.binary_name, &self.rust_thunk_name)
         ];
 
+        // Lets flow analysis see that the wrapper doesn't return either
+        if self.diverges {
+            attributes.push(ast::Attribute::marker("DoesNotReturn"));
+        }
+
+        if let BindingType::Simple(SimpleBindingType { descriptor: Some(core::BindgenTypeDescriptor::Bool), .. }) = self.return_ty {
+            attributes.push(ast::Attribute::return_marshal_as(BOOL_MARSHAL_AS));
+        }
+
         let return_ty = self.return_ty.native_type();
 
-        let args = self.args
+        let args: Vec<_> = self.args
             .iter()
-            .map(|arg| ast::MethodArgument {
+            .zip(&self.arg_marshalling)
+            .map(|(arg, marshal_as)| ast::MethodArgument {
                 name: arg.rust_name.as_str().into(),
                 ty: arg.ty.native_type(),
+                // The runtime marshaller only knows the older spelling
+                direction: match arg.direction {
+                    ast::Direction::RefReadonly => ast::Direction::In,
+                    direction => direction,
+                },
+                is_this: false,
+                marshal_as: marshal_as.clone(),
             })
             .collect();
 
+        let is_unsafe = signature_is_unsafe(&args, &return_ty);
+
         ast::Method {
+            doc: None,
             attributes,
-            is_public: false,
+            visibility: self.visibility.externs,
             is_static: true,
             is_extern: true,
-            is_unsafe: false,
+            is_unsafe,
+            is_override: false,
             name: self.rust_thunk_name.to_string(),
             return_ty,
             args,
@@ -859,70 +2176,339 @@ impl BindingMethod {
         }
     }
 
-    fn thunk_method(&self) -> ast::Method {
-        let attributes = Vec::new();
+    fn thunk_return_ty(&self, thunk: &CsThunk) -> ast::CSharpType {
+        // TODO: Make this the idiomatic type for every type + add the relevant marshalling to the body.
+        match &thunk.return_ty {
+            Some(ty) => ty.clone(),
+            None => self.return_ty.native_type(),
+        }
+    }
 
-        let name = self.cs_name.to_string();
+    /// Values covering the edge cases of the type a round trip function takes and returns, eg the
+    /// min and max of an integer type, or NaN and the infinities of a float type.
+    fn roundtrip_values(descriptor: &core::BindgenFunctionDescriptor) -> Result<Vec<String>, &'static str> {
+        let ty = match descriptor.arguments.as_slice() {
+            [arg] if arg.ty == descriptor.return_ty && descriptor.error_enum.is_none() => &arg.ty,
+            _ => return Err("Round trip functions must return their single argument's type unchanged"),
+        };
 
-        // TODO: Make this the idiomatic type + add the relevant marshalling to the body.
-        let return_ty = self.return_ty.native_type();
+        let values = match ty {
+            core::BindgenTypeDescriptor::Bool => vec!["true".to_string(), "false".to_string()],
+            core::BindgenTypeDescriptor::Int { signed, .. } | core::BindgenTypeDescriptor::Size { signed } => {
+                let cs_type = BindingType::try_from(ty.clone())?.native_type();
+                let mut values = vec![format!("{}.MinValue", cs_type), format!("{}.MaxValue", cs_type)];
+
+                // The minimum of an unsigned type is already zero
+                if *signed {
+                    values.insert(1, format!("default({})", cs_type));
+                }
+                values
+            }
+            // The caller sees the netstandard2.0 build of the bindings, where Half is always its
+            // UInt16 bits, so has none of System.Half's members. These are the bits of its
+            // MinValue, MaxValue, Epsilon, NaN, PositiveInfinity, and NegativeInfinity.
+            core::BindgenTypeDescriptor::Float { width: 16 } => {
+                ["0xFBFF", "0x7BFF", "0x0001", "0x7E00", "0x7C00", "0xFC00"]
+                    .iter()
+                    .map(|bits| format!("(Half){}", bits))
+                    .collect()
+            }
+            core::BindgenTypeDescriptor::Float { .. } => {
+                let cs_type = BindingType::try_from(ty.clone())?.native_type();
+                ["MinValue", "MaxValue", "Epsilon", "NaN", "PositiveInfinity", "NegativeInfinity"]
+                    .iter()
+                    .map(|value| format!("{}.{}", cs_type, value))
+                    .collect()
+            }
+            _ => return Err("Round trip tests can only pass integers, floats, and bools"),
+        };
+
+        Ok(values)
+    }
+
+    /// A test passing each of the round trip values through the first of this method's wrappers,
+    /// and checking that it comes back unchanged.
+    ///
+    /// The expected type is given explicitly, as the wrapper may return a wider type than it was
+    /// passed.
+    fn roundtrip_test_method(&self, methods_class: &str, values: &[String]) -> ast::Method {
+        let thunk = self.cs_thunks.first().expect("Every method has at least one wrapper");
+        let return_ty = self.thunk_return_ty(thunk);
 
-        let args = self.args
+        let body = values
             .iter()
-            .map(|arg| ast::MethodArgument {
-                name: arg.cs_name.as_str().into(),
-                ty: arg.ty.idiomatic_type(),
+            .map(|value| {
+                let call = ast::MethodInvocation {
+                    target: Some(methods_class.into()),
+                    method_name: self.cs_name.as_str().into(),
+                    args: vec![(ast::Direction::ByValue, value.as_str().into())],
+                };
+
+                Box::new(ast::Statement {
+                    expr: Box::new(ast::MethodInvocation {
+                        target: Some("Assert".into()),
+                        method_name: ast::Ident(format!("Equal<{}>", return_ty)),
+                        args: vec![
+                            (ast::Direction::ByValue, value.as_str().into()),
+                            (ast::Direction::ByValue, ast::Ident(call.to_string())),
+                        ],
+                    }),
+                }) as Box<dyn ast::AstNode>
             })
             .collect();
-        
-        let body = Some(self.cs_thunk_body
-            .as_ref()
-            .unwrap()
-            .to_ast_nodes()
-        );
 
         ast::Method {
-            attributes,
-            is_public: true,
-            is_static: true,
+            doc: None,
+            attributes: vec![ast::Attribute::marker("Fact")],
+            visibility: ast::Visibility::Public,
+            is_static: false,
             is_extern: false,
             is_unsafe: false,
-            name,
-            return_ty,
-            args,
-            body,
+            is_override: false,
+            name: format!("{}RoundTrips", self.cs_name),
+            return_ty: ast::CSharpType::Void,
+            args: Vec::new(),
+            body: Some(body),
         }
     }
-}
 
+    /// A benchmark calling the first of this method's wrappers, through the given class.
+    ///
+    /// Every argument is just the default value of its type, which most native functions won't
+    /// accept, so the generated harness is only a starting point.
+    fn bench_method(&self, methods_class: &str) -> ast::Method {
+        let thunk = self.cs_thunks.first().expect("Every method has at least one wrapper");
+        let mut body: Vec<Box<dyn ast::AstNode>> = Vec::new();
+        if !thunk.args.is_empty() {
+            body.push(Box::new(ast::RawLines {
+                lines: vec!["// TODO: Replace these placeholder arguments with realistic ones".to_string()],
+            }));
+        }
 
-struct BindingStructField {
-    /// The name of this field in the generated C# (CamelCase transform rust_name)
-    cs_name: String,
+        let mut call_args = Vec::new();
+        for (name, ty, direction) in &thunk.args {
+            let arg = match direction {
+                ast::Direction::ByValue => ast::Ident(format!("default({})", ty)),
+                ast::Direction::Out => ast::Ident::new("_"),
 
-    /// The type of this field. Restricted to simple binding types to make the entire struct FFI stable.
-    ty: SimpleBindingType,
-}
+                // Passing by reference needs somewhere to refer to
+                ast::Direction::In | ast::Direction::Ref | ast::Direction::RefReadonly => {
+                    body.push(Box::new(ast::VariableDeclaration { name: name.as_str().into(), ty: ty.clone() }));
+                    body.push(Box::new(ast::Statement {
+                        expr: Box::new(ast::BinaryExpression {
+                            lhs: Box::new(ast::Ident::new(name)),
+                            rhs: Box::new(ast::Ident(format!("default({})", ty))),
+                            operation_sym: "=",
+                        }),
+                    }));
+                    ast::Ident::new(name)
+                }
+            };
+            call_args.push((*direction, arg));
+        }
+
+        let call = Box::new(ast::MethodInvocation {
+            target: Some(methods_class.into()),
+            method_name: self.cs_name.as_str().into(),
+            args: call_args,
+        });
+
+        // Returning the result stops the call being optimised away
+        let return_ty = self.thunk_return_ty(thunk);
+        body.push(match return_ty {
+            ast::CSharpType::Void => Box::new(ast::Statement { expr: call }),
+            _ => Box::new(ast::ReturnStatement { value: Some(call) }),
+        });
+
+        ast::Method {
+            doc: None,
+            attributes: vec![ast::Attribute::marker("Benchmark")],
+            visibility: ast::Visibility::Public,
+            is_static: false,
+            is_extern: false,
+            is_unsafe: false,
+            is_override: false,
+            name: self.cs_name.to_string(),
+            return_ty,
+            args: Vec::new(),
+            body: Some(body),
+        }
+    }
+
+    /// Documents anything about the bound function that the wrapper's signature can't express.
+    fn wrapper_doc(&self) -> Option<ast::DocComment> {
+        let mut remarks = cfg_doc(&self.cfg).map(|d| d.remarks).unwrap_or_default();
+
+        if let Some(reason) = &self.must_use {
+            remarks.push("The return value of this method should not be ignored.".to_string());
+            if !reason.is_empty() {
+                remarks.push(reason.clone());
+            }
+        }
+
+        if remarks.is_empty() {
+            None
+        } else {
+            Some(ast::DocComment { remarks })
+        }
+    }
+
+    fn thunk_method(&self, thunk: &CsThunk) -> ast::Method {
+        let mut attributes = Vec::new();
+        if self.diverges {
+            attributes.push(ast::Attribute::marker("DoesNotReturn"));
+        }
+        if let (Some(reason), true) = (&self.must_use, self.must_use_attribute) {
+            attributes.push(ast::Attribute {
+                name: "MustUse".to_string(),
+                positional_parameters: Vec::new(),
+                named_parameters: match reason.is_empty() {
+                    true => Vec::new(),
+                    false => vec![("Reason".into(), ast::LiteralValue::QuotedString(reason.clone()))],
+                },
+                condition: None,
+            });
+        }
+        if self.skip_locals_init && self.custom_body.is_none() && thunk.body.allocates_on_stack() {
+            attributes.push(ast::Attribute {
+                condition: Some(SKIP_LOCALS_INIT_CONDITION.to_string()),
+                ..ast::Attribute::marker("System.Runtime.CompilerServices.SkipLocalsInit")
+            });
+        }
+
+        let name = self.cs_name.to_string();
+
+        let return_ty = self.thunk_return_ty(thunk);
+
+        let is_builder = self.is_builder();
+        let args: Vec<_> = thunk.args
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, ty, direction))| ast::MethodArgument {
+                name: name.as_str().into(),
+                ty: ty.clone(),
+                direction: *direction,
+                is_this: is_builder && idx == 0,
+                marshal_as: None,
+            })
+            .collect();
+
+        let body_nodes = match &self.custom_body {
+            Some(body) => vec![Box::new(ast::RawLines::dedented(body)) as Box<dyn ast::AstNode>],
+            None => thunk.body.to_ast_nodes(),
+        };
+
+        let body = Some(
+            self.platform_guard()
+                .into_iter()
+                .chain(body_nodes)
+                .collect()
+        );
+
+        // Pointers in the body are already scoped to an unsafe block, so only the signature matters
+        let is_unsafe = signature_is_unsafe(&args, &return_ty);
+
+        ast::Method {
+            doc: self.wrapper_doc(),
+            attributes,
+            visibility: self.visibility.wrappers,
+            is_static: true,
+            is_extern: false,
+            is_unsafe,
+            is_override: false,
+            name,
+            return_ty,
+            args,
+            body,
+        }
+    }
+}
+
+
+struct BindingStructField {
+    /// The name of this field in the generated C# (CamelCase transform rust_name)
+    cs_name: String,
+
+    /// The type of this field. Restricted to simple binding types to make the entire struct FFI stable.
+    ty: SimpleBindingType,
+
+    /// The UnmanagedType this field is marshalled as, if its C# type has a different default layout.
+    marshal_as: Option<&'static str>,
+
+    /// The length of the inline buffer this field is marshalled as, if it is a string buffer.
+    size_const: Option<usize>,
+
+    /// The CharSet a string buffer field is encoded with, which must be that of its whole struct.
+    charset: Option<&'static str>,
+}
 
 impl BindingStructField {
     fn new(descriptor: &core::BindgenStructFieldDescriptor) -> Result<Self, &'static str> {
         let cs_name = descriptor.name.to_camel_case();
 
+        if descriptor.string_buffer {
+            // ByValTStr copies up to the first nul, so the buffer holds at most `len - 1` characters
+            let (charset, len) = match &descriptor.ty {
+                core::BindgenTypeDescriptor::Array { elem_type, len } => match **elem_type {
+                    core::BindgenTypeDescriptor::Int { width: 8, signed: false } => ("Ansi", *len),
+                    core::BindgenTypeDescriptor::Int { width: 16, signed: false } => ("Unicode", *len),
+                    _ => return Err("String buffer fields must be arrays of u8 or u16"),
+                },
+                _ => return Err("String buffer fields must be arrays of u8 or u16"),
+            };
+
+            return Ok(Self {
+                cs_name,
+                ty: SimpleBindingType {
+                    descriptor: Some(descriptor.ty.clone()),
+                    cs_type: ast::CSharpType::String,
+                },
+                marshal_as: Some("ByValTStr"),
+                size_const: Some(len),
+                charset: Some(charset),
+            });
+        }
+
+        let mut marshal_as = None;
         let ty = match descriptor.ty.clone().try_into()? {
-            BindingType::Simple(s) => s,
+            BindingType::Simple(SimpleBindingType { descriptor: Some(core::BindgenTypeDescriptor::Str { .. }), .. }) => {
+                return Err("Can't create bindings for structs with string fields yet")
+            }
+            BindingType::Simple(s) => {
+                if s.descriptor == Some(core::BindgenTypeDescriptor::Bool) {
+                    marshal_as = Some(BOOL_MARSHAL_AS);
+                }
+                s
+            }
             _ => return Err("Can't create bindings for structs with non-ffi-stable fields"),
         };
 
         Ok(Self {
             cs_name,
             ty,
+            marshal_as,
+            size_const: None,
+            charset: None,
         })
     }
 
     fn to_ast_field(&self) -> ast::Field {
+        let marshal_as = self.marshal_as.map(|m| {
+            let mut attr = ast::Attribute::marshal_as(m);
+            if let Some(size_const) = self.size_const {
+                attr.named_parameters.push((
+                    ast::Ident::new("SizeConst"),
+                    ast::LiteralValue::Number(size_const as i128),
+                ));
+            }
+            attr
+        });
+
         ast::Field {
+            attributes: marshal_as.into_iter().collect(),
             name: self.cs_name.clone(),
             ty: self.ty.cs_type.clone(),
+            const_value: None,
         }
     }
 }
@@ -936,26 +2522,404 @@ struct BindingStruct {
 
     /// Set of methods to grant this struct
     methods: Vec<BindingMethod>,
+
+    /// The cfg predicate that the bound struct was gated on, if any.
+    cfg: Option<String>,
+
+    /// Whether to generate a stub marshaller for the struct, see [`BindingStruct::marshaller_object`].
+    native_marshalling: bool,
+
+    /// The idiomatic C# type to generate conversions to and from, if any.
+    as_type: Option<StructAsType>,
+
+    /// The CharSet of the struct's layout, if it has string buffer fields to be encoded with it.
+    charset: Option<&'static str>,
+}
+
+/// An idiomatic C# type which a bound struct can be converted to and from.
+enum StructAsType {
+    /// A duration made up of whole seconds and a number of nanoseconds, named by the C# names of
+    /// the two fields.
+    TimeSpan { seconds: String, nanos: String },
+}
+
+impl StructAsType {
+    fn new(as_type: &str, descriptor: &core::BindgenStructDescriptor) -> Result<Self, &'static str> {
+        let int_field = |names: &[&str]| {
+            descriptor.fields
+                .iter()
+                .find(|f| names.contains(&f.name.as_str()))
+                .filter(|f| matches!(f.ty, core::BindgenTypeDescriptor::Int { .. }))
+                .map(|f| f.name.to_camel_case())
+        };
+
+        match as_type {
+            "TimeSpan" => match (int_field(core::TIMESPAN_SECONDS_FIELDS), int_field(core::TIMESPAN_NANOS_FIELDS)) {
+                (Some(seconds), Some(nanos)) => Ok(StructAsType::TimeSpan { seconds, nanos }),
+                _ => Err("Structs converted to a TimeSpan must have integer `secs` and `nanos` fields"),
+            },
+            _ => Err("Unrecognized type to convert a struct to"),
+        }
+    }
 }
 
 impl BindingStruct {
     fn new(descriptor: &core::BindgenStructDescriptor) -> Result<Self, &'static str> {
         let fields = descriptor.fields
             .iter()
-            .map(|f| BindingStructField::new(&f))
+            .map(BindingStructField::new)
             .collect::<Result<Vec<_>, _>>()?;
 
         let name = descriptor.name.to_string();
 
+        let mut charsets = fields.iter().filter_map(|f| f.charset);
+        let charset = charsets.next();
+        if charsets.any(|c| Some(c) != charset) {
+            return Err("All the string buffer fields of a struct must have the same element type");
+        }
+
         Ok(Self {
             name,
             fields,
+            charset,
             methods: Vec::new(),
+            cfg: descriptor.cfg.clone(),
+            native_marshalling: descriptor.native_marshalling && !descriptor.fields.is_empty(),
+            as_type: descriptor.as_type
+                .as_deref()
+                .map(|as_type| StructAsType::new(as_type, descriptor))
+                .transpose()?,
+        })
+    }
+
+    /// A `ToX` method and a static `FromX` method converting between the struct and the idiomatic
+    /// C# type it was marked as.
+    ///
+    /// Both conversions are checked, so throw an OverflowException rather than silently truncating
+    /// a value which doesn't fit. A TimeSpan only has a resolution of 100ns, so any remaining
+    /// nanoseconds are dropped when converting to one.
+    fn as_type_methods(&self, as_type: &StructAsType) -> Vec<ast::Method> {
+        use ast::CSharpType as CS;
+
+        let this_ty = CS::Struct { name: self.name.as_str().into() };
+        let method = |name: String, is_static, return_ty, args: Vec<(&str, CS)>, body| ast::Method {
+            doc: None,
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Public,
+            is_static,
+            is_extern: false,
+            is_unsafe: false,
+            is_override: false,
+            name,
+            return_ty,
+            args: args
+                .into_iter()
+                .map(|(name, ty)| ast::MethodArgument {
+                    name: name.into(),
+                    ty,
+                    direction: ast::Direction::ByValue,
+                    is_this: false,
+                    marshal_as: None,
+                })
+                .collect(),
+            body: Some(body),
+        };
+
+        let binary = |lhs: Box<dyn ast::AstNode>, operation_sym, rhs: Box<dyn ast::AstNode>| -> Box<dyn ast::AstNode> {
+            Box::new(ast::BinaryExpression { lhs, rhs, operation_sym })
+        };
+        let cast = |ty, element: Box<dyn ast::AstNode>| -> Box<dyn ast::AstNode> {
+            Box::new(ast::Cast { ty, element })
+        };
+        let checked = |element| -> Box<dyn ast::AstNode> {
+            Box::new(ast::OverflowContext { checked: true, element })
+        };
+        let ident = |name: &str| -> Box<dyn ast::AstNode> { Box::new(ast::Ident::new(name)) };
+        let field_ty = |cs_name: &str| {
+            self.fields
+                .iter()
+                .find(|f| f.cs_name == cs_name)
+                .map(|f| f.ty.cs_type.clone())
+                .expect("Conversions are only created for fields of the struct")
+        };
+        let assign = |field: &str, value| -> Box<dyn ast::AstNode> {
+            Box::new(ast::Statement { expr: binary(ident(&format!("result.{}", field)), "=", value) })
+        };
+
+        match as_type {
+            StructAsType::TimeSpan { seconds, nanos } => {
+                let time_span = CS::Struct { name: "TimeSpan".into() };
+
+                let ticks = checked(binary(
+                    binary(cast(CS::Int64, ident(seconds)), "*", ident("TimeSpan.TicksPerSecond")),
+                    "+",
+                    binary(cast(CS::Int64, ident(nanos)), "/", Box::new(ast::LiteralValue::Number(100))),
+                ));
+                let to_body: Vec<Box<dyn ast::AstNode>> = vec![Box::new(ast::ReturnStatement {
+                    value: Some(Box::new(ast::ObjectCreation { ty: time_span.clone(), args: vec![ticks] })),
+                })];
+
+                let from_body: Vec<Box<dyn ast::AstNode>> = vec![
+                    Box::new(ast::VariableDeclaration { name: "result".into(), ty: this_ty.clone() }),
+                    assign(seconds, checked(cast(
+                        field_ty(seconds),
+                        binary(ident("value.Ticks"), "/", ident("TimeSpan.TicksPerSecond")),
+                    ))),
+                    assign(nanos, checked(cast(
+                        field_ty(nanos),
+                        binary(
+                            binary(ident("value.Ticks"), "%", ident("TimeSpan.TicksPerSecond")),
+                            "*",
+                            Box::new(ast::LiteralValue::Number(100)),
+                        ),
+                    ))),
+                    Box::new(ast::ReturnStatement { value: Some(ident("result")) }),
+                ];
+
+                vec![
+                    method("ToTimeSpan".to_string(), false, time_span.clone(), Vec::new(), to_body),
+                    method(
+                        "FromTimeSpan".to_string(), true, this_ty.clone(),
+                        vec![("value", time_span)],
+                        from_body,
+                    ),
+                ]
+            }
+        }
+    }
+
+    fn marshaller_name(&self) -> String {
+        format!("{}Marshaller", self.name)
+    }
+
+    /// A stub custom marshaller for the struct, which the struct names in a `[NativeMarshalling]`
+    /// attribute.
+    ///
+    /// This only has an effect on methods bound with LibraryImport rather than DllImport. It
+    /// passes the struct through unchanged, as a starting point for hand written conversions.
+    ///
+    /// The marshalling types are only available from .NET 7, so the marshaller is conditionally
+    /// compiled.
+    fn marshaller_object(&self) -> Box<dyn ast::AstNode> {
+        use ast::CSharpType as CS;
+
+        let this_ty = CS::Struct { name: self.name.as_str().into() };
+        let conversion = |name: &str, arg_name: &str, todo: &str| ast::Method {
+            doc: None,
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Public,
+            is_static: true,
+            is_extern: false,
+            is_unsafe: false,
+            is_override: false,
+            name: name.to_string(),
+            return_ty: this_ty.clone(),
+            args: vec![ast::MethodArgument {
+                name: arg_name.into(),
+                ty: this_ty.clone(),
+                direction: ast::Direction::ByValue,
+                is_this: false,
+                marshal_as: None,
+            }],
+            body: Some(vec![
+                Box::new(ast::RawLines { lines: vec![format!("// TODO: {}", todo)] }),
+                Box::new(ast::ReturnStatement {
+                    value: Some(Box::new(ast::Ident::new(arg_name))),
+                }),
+            ]),
+        };
+
+        let marshaller = ast::Object {
+            doc: Some(ast::DocComment {
+                remarks: vec![
+                    format!("Converts {} to and from its native representation for LibraryImport'd methods.", self.name),
+                ],
+            }),
+            attributes: vec![ast::Attribute {
+                name: "System.Runtime.InteropServices.Marshalling.CustomMarshaller".to_string(),
+                positional_parameters: vec![
+                    ast::LiteralValue::TypeOf(self.name.clone()),
+                    ast::LiteralValue::EnumValue(
+                        "System.Runtime.InteropServices.Marshalling.MarshalMode".to_string(),
+                        "Default".to_string(),
+                    ),
+                    ast::LiteralValue::TypeOf(self.marshaller_name()),
+                ],
+                named_parameters: Vec::new(),
+                condition: None,
+            }],
+            object_type: ast::ObjectType::Class,
+            is_static: true,
+            name: self.marshaller_name(),
+            interfaces: Vec::new(),
+            methods: vec![
+                conversion("ConvertToUnmanaged", "managed", "convert to the native representation"),
+                conversion("ConvertToManaged", "unmanaged", "convert from the native representation"),
+            ],
+            fields: Vec::new(),
+            regions: Vec::new(),
+            is_partial: false,
+        };
+
+        Box::new(ast::Conditional {
+            condition: NATIVE_MARSHALLING_CONDITION.to_string(),
+            children: vec![Box::new(marshaller)],
         })
     }
 
-    fn to_ast_object(&self) -> ast::Object {
-        let is_static = self.fields.len() == 0;
+    /// Value based equality methods, so that the struct doesn't fall back on the slow reflection
+    /// based default implementations.
+    fn equality_methods(&self) -> Vec<ast::Method> {
+        use ast::CSharpType as CS;
+
+        let this_ty = CS::Struct { name: self.name.as_str().into() };
+
+        let method = |name: &str, is_static, is_override, return_ty, args: Vec<(&str, CS)>, body| {
+            ast::Method {
+                doc: None,
+                attributes: Vec::new(),
+                visibility: ast::Visibility::Public,
+                is_static,
+                is_extern: false,
+                is_unsafe: false,
+                is_override,
+                name: name.to_string(),
+                return_ty,
+                args: args
+                    .into_iter()
+                    .map(|(name, ty)| ast::MethodArgument {
+                        name: name.into(),
+                        ty,
+                        direction: ast::Direction::ByValue,
+                        is_this: false,
+                        marshal_as: None,
+                    })
+                    .collect(),
+                body: Some(body),
+            }
+        };
+
+        let call = |target: &str, method_name: &str, arg: &str| -> Box<dyn ast::AstNode> {
+            Box::new(ast::MethodInvocation {
+                target: Some(target.into()),
+                method_name: method_name.into(),
+                args: vec![(ast::Direction::ByValue, arg.into())],
+            })
+        };
+
+        let returning = |value: Box<dyn ast::AstNode>| -> Vec<Box<dyn ast::AstNode>> {
+            vec![Box::new(ast::ReturnStatement { value: Some(value) })]
+        };
+
+        // String buffer fields are null in a default constructed struct, so can't be called through
+        let field_equal = |f: &BindingStructField| -> Box<dyn ast::AstNode> {
+            match f.ty.cs_type {
+                CS::String => Box::new(ast::MethodInvocation {
+                    target: Some("string".into()),
+                    method_name: "Equals".into(),
+                    args: vec![
+                        (ast::Direction::ByValue, f.cs_name.as_str().into()),
+                        (ast::Direction::ByValue, format!("other.{}", f.cs_name).as_str().into()),
+                    ],
+                }),
+                _ => call(&f.cs_name, "Equals", &format!("other.{}", f.cs_name)),
+            }
+        };
+
+        let fields_equal = self.fields
+            .iter()
+            .map(field_equal)
+            .reduce(|lhs, rhs| Box::new(ast::BinaryExpression { lhs, rhs, operation_sym: "&&" }))
+            .expect("Structs without fields are bound as static classes");
+
+        let is_equal_struct = Box::new(ast::BinaryExpression {
+            lhs: Box::new(ast::IsPattern {
+                element: Box::new(ast::Ident::new("obj")),
+                ty: this_ty.clone(),
+                binding: "other".into(),
+            }),
+            rhs: Box::new(ast::MethodInvocation {
+                target: None,
+                method_name: "Equals".into(),
+                args: vec![(ast::Direction::ByValue, "other".into())],
+            }),
+            operation_sym: "&&",
+        });
+
+        let mut hash_body: Vec<Box<dyn ast::AstNode>> = vec![
+            Box::new(ast::VariableDeclaration { name: "hash".into(), ty: CS::Int32 }),
+            Box::new(ast::Statement {
+                expr: Box::new(ast::BinaryExpression {
+                    lhs: Box::new(ast::Ident::new("hash")),
+                    rhs: Box::new(ast::LiteralValue::Number(17)),
+                    operation_sym: "=",
+                }),
+            }),
+        ];
+        for field in &self.fields {
+            let combined = ast::BinaryExpression {
+                lhs: Box::new(ast::BinaryExpression {
+                    lhs: Box::new(ast::Ident::new("hash")),
+                    rhs: Box::new(ast::LiteralValue::Number(31)),
+                    operation_sym: "*",
+                }),
+                rhs: Box::new(match field.ty.cs_type {
+                    CS::String => ast::MethodInvocation {
+                        target: Some("System.Collections.Generic.EqualityComparer<string>.Default".into()),
+                        method_name: "GetHashCode".into(),
+                        args: vec![(ast::Direction::ByValue, field.cs_name.as_str().into())],
+                    },
+                    _ => ast::MethodInvocation {
+                        target: Some(field.cs_name.as_str().into()),
+                        method_name: "GetHashCode".into(),
+                        args: Vec::new(),
+                    },
+                }),
+                operation_sym: "+",
+            };
+
+            hash_body.push(Box::new(ast::Statement {
+                expr: Box::new(ast::BinaryExpression {
+                    lhs: Box::new(ast::Ident::new("hash")),
+                    rhs: Box::new(ast::OverflowContext {
+                        checked: false,
+                        element: Box::new(combined),
+                    }),
+                    operation_sym: "=",
+                }),
+            }));
+        }
+        hash_body.push(Box::new(ast::ReturnStatement {
+            value: Some(Box::new(ast::Ident::new("hash"))),
+        }));
+
+        vec![
+            method(
+                "Equals", false, false, CS::Bool,
+                vec![("other", this_ty.clone())],
+                returning(fields_equal),
+            ),
+            method(
+                "Equals", false, true, CS::Bool,
+                vec![("obj", CS::Struct { name: "Object".into() })],
+                returning(is_equal_struct),
+            ),
+            method("GetHashCode", false, true, CS::Int32, Vec::new(), hash_body),
+            method(
+                "operator ==", true, false, CS::Bool,
+                vec![("lhs", this_ty.clone()), ("rhs", this_ty.clone())],
+                returning(call("lhs", "Equals", "rhs")),
+            ),
+            method(
+                "operator !=", true, false, CS::Bool,
+                vec![("lhs", this_ty.clone()), ("rhs", this_ty)],
+                returning(Box::new(ast::Negation { element: call("lhs", "Equals", "rhs") })),
+            ),
+        ]
+    }
+
+    fn to_ast_object(&self, config: &CodegenConfig) -> ast::Object {
+        let is_static = self.fields.is_empty();
         let object_type = if is_static {
             ast::ObjectType::Class
         } else {
@@ -969,19 +2933,254 @@ impl BindingStruct {
             .map(|f| f.to_ast_field())
             .collect();
 
-        let methods = self.methods
+        let mut methods: Vec<_> = self.methods
             .iter()
             .flat_map(|m| m.to_ast_methods())
             .collect();
 
+        let mut interfaces = Vec::new();
+        if config.struct_equality && !is_static {
+            interfaces.push(format!("IEquatable<{}>", self.name));
+            methods.extend(self.equality_methods());
+        }
+
+        if let Some(as_type) = &self.as_type {
+            methods.extend(self.as_type_methods(as_type));
+        }
+
+        let mut layout = ast::Attribute::struct_layout("Sequential");
+        if let Some(charset) = self.charset {
+            layout.named_parameters.push((
+                ast::Ident::new("CharSet"),
+                ast::LiteralValue::EnumValue("CharSet".to_string(), charset.to_string()),
+            ));
+        }
+
+        let mut attributes = vec![layout];
+        attributes.extend(config.generated_code_attribute());
+        if self.native_marshalling {
+            attributes.push(ast::Attribute {
+                name: "System.Runtime.InteropServices.Marshalling.NativeMarshalling".to_string(),
+                positional_parameters: vec![ast::LiteralValue::TypeOf(self.marshaller_name())],
+                named_parameters: Vec::new(),
+                condition: Some(NATIVE_MARSHALLING_CONDITION.to_string()),
+            });
+        }
+
         ast::Object {
-            attributes: vec![ast::Attribute::struct_layout("Sequential")],
+            doc: cfg_doc(&self.cfg),
+            attributes,
             object_type,
             is_static,
             name,
+            interfaces,
             methods,
             fields,
+            regions: Vec::new(),
+            is_partial: false,
+        }
+    }
+}
+
+struct BindingHandle {
+    /// The name of the opaque type in both the bound Rust, and the generated C#
+    name: String,
+
+    /// The cfg predicate that the bound type was gated on, if any.
+    cfg: Option<String>,
+
+    /// The qualified C# name of the method to call from `DisposeAsync`, if the handle should
+    /// implement IAsyncDisposable.
+    async_release: Option<String>,
+}
+
+impl BindingHandle {
+    fn new(descriptor: &core::BindgenOpaqueDescriptor, async_release: Option<String>) -> Self {
+        Self {
+            name: descriptor.name.to_string(),
+            cfg: descriptor.cfg.clone(),
+            async_release,
+        }
+    }
+
+    /// Frees the handle on the thread pool, as the release function may block.
+    ///
+    /// Lambdas within a struct can't capture `this`, so the handle is copied to a local first.
+    fn dispose_async_method(&self, release: &str) -> ast::Method {
+        let release_call = ast::MethodInvocation {
+            target: None,
+            method_name: release.into(),
+            args: vec![(ast::Direction::ByValue, "handle".into())],
+        };
+
+        let task = ast::MethodInvocation {
+            target: Some("Task".into()),
+            method_name: "Run".into(),
+            args: vec![(
+                ast::Direction::ByValue,
+                ast::Ident(ast::Lambda { body: Box::new(release_call) }.to_string()),
+            )],
+        };
+
+        ast::Method {
+            doc: None,
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Public,
+            is_static: false,
+            is_extern: false,
+            is_unsafe: false,
+            is_override: false,
+            name: "DisposeAsync".to_string(),
+            return_ty: ast::CSharpType::Struct { name: "ValueTask".into() },
+            args: Vec::new(),
+            body: Some(vec![
+                Box::new(ast::VariableDeclaration {
+                    name: "handle".into(),
+                    ty: ast::CSharpType::Struct { name: self.name.as_str().into() },
+                }),
+                Box::new(ast::Statement {
+                    expr: Box::new(ast::BinaryExpression {
+                        lhs: Box::new(ast::Ident::new("handle")),
+                        rhs: Box::new(ast::Ident::new("this")),
+                        operation_sym: "=",
+                    }),
+                }),
+                Box::new(ast::ReturnStatement {
+                    value: Some(Box::new(ast::ObjectCreation {
+                        ty: ast::CSharpType::Struct { name: "ValueTask".into() },
+                        args: vec![Box::new(task)],
+                    })),
+                }),
+            ]),
+        }
+    }
+
+    /// A struct wrapping the raw pointer, so that handles to different types can't be mixed up.
+    fn to_ast_object(&self, config: &CodegenConfig) -> ast::Object {
+        ast::Object {
+            doc: cfg_doc(&self.cfg),
+            attributes: std::iter::once(ast::Attribute::struct_layout("Sequential"))
+                .chain(config.generated_code_attribute())
+                .collect(),
+            object_type: ast::ObjectType::Struct,
+            is_static: false,
+            name: self.name.clone(),
+            interfaces: self.async_release
+                .iter()
+                .map(|_| "IAsyncDisposable".to_string())
+                .collect(),
+            methods: self.async_release
+                .iter()
+                .map(|release| self.dispose_async_method(release))
+                .collect(),
+            fields: vec![ast::Field {
+                attributes: Vec::new(),
+                name: "Ptr".to_string(),
+                ty: ast::CSharpType::intptr(),
+                const_value: None,
+            }],
+            regions: Vec::new(),
+            is_partial: false,
+        }
+    }
+}
+
+struct BindingEnum {
+    /// The name of the enum in both the bound Rust, and the generated C#
+    name: String,
+
+    /// The C# type of the enum's repr
+    repr: ast::CSharpType,
+
+    /// The name of each variant, paired with its discriminant.
+    variants: Vec<(String, i128)>,
+
+    /// Whether the enum is C-like, and so can be modelled as a C# enum.
+    c_like: bool,
+
+    /// The cfg predicate that the bound enum was gated on, if any.
+    cfg: Option<String>,
+}
+
+impl BindingEnum {
+    fn new(descriptor: &core::BindgenEnumDescriptor) -> Result<Self, &'static str> {
+        let repr = match BindingType::try_from(*descriptor.repr.clone())? {
+            BindingType::Simple(s) => s.cs_type,
+            BindingType::Complex(_) => return Err("Enums must be represented by an integer type"),
+        };
+
+        // The base of a C# enum can only be one of the fixed width integers
+        if descriptor.c_like && repr.integer_keyword().is_none() {
+            return Err("C-like enums must be represented by a fixed width integer type");
+        }
+
+        let variants = descriptor.variants
+            .iter()
+            .map(|v| (v.name.to_string(), v.discriminant))
+            .collect();
+
+        Ok(Self {
+            name: descriptor.name.to_string(),
+            repr,
+            variants,
+            c_like: descriptor.c_like,
+            cfg: descriptor.cfg.clone(),
+        })
+    }
+
+    /// C-like enums map directly onto a C# enum.
+    ///
+    /// C# enums can't carry any data though, so for any other enum just the discriminants are
+    /// exposed, as constants in a static class.
+    fn to_ast_node(&self, config: &CodegenConfig) -> Box<dyn ast::AstNode> {
+        let mut doc = cfg_doc(&self.cfg);
+        let attributes = config.generated_code_attribute().into_iter().collect();
+
+        let note = if self.c_like {
+            // The thunks panic on any other value, which aborts as it can't unwind into the caller
+            "Passing a value that isn't one of these variants as an argument to the native library \
+                aborts the process.".to_string()
+        } else {
+            format!(
+                "The variants of the Rust enum {} carry data, so only their discriminants are exposed.",
+                self.name
+            )
+        };
+        match &mut doc {
+            Some(doc) => doc.remarks.push(note),
+            None => doc = Some(ast::DocComment { remarks: vec![note] }),
+        }
+
+        if self.c_like {
+            return Box::new(ast::Enum {
+                doc,
+                attributes,
+                name: self.name.clone(),
+                underlying_ty: self.repr.clone(),
+                variants: self.variants.clone(),
+            });
         }
+
+        Box::new(ast::Object {
+            doc,
+            attributes,
+            object_type: ast::ObjectType::Class,
+            is_static: true,
+            name: self.name.clone(),
+            interfaces: Vec::new(),
+            methods: Vec::new(),
+            fields: self.variants
+                .iter()
+                .map(|(name, discriminant)| ast::Field {
+                    attributes: Vec::new(),
+                    name: name.clone(),
+                    ty: self.repr.clone(),
+                    const_value: Some(ast::LiteralValue::Number(*discriminant)),
+                })
+                .collect(),
+            regions: Vec::new(),
+            is_partial: false,
+        })
     }
 }
 
@@ -994,92 +3193,1105 @@ struct CodegenInfo<'a> {
     ///
     /// It should be sufficient to use this string as the first argument to a DllImportAttribute.
     lib_name: String,
+
+    config: &'a CodegenConfig,
 }
 
 impl<'a> CodegenInfo<'a> {
-    fn new(data: &'a BindgenData) -> Self {
+    fn new(data: &'a BindgenData, config: &'a CodegenConfig) -> Self {
         let lib_name = data.source_file.bin_base_name();
         Self {
             data,
             lib_name,
+            config,
         }
     }
 
-    fn slice_abi_obj() -> ast::Object {
+    fn slice_abi_obj(config: &CodegenConfig) -> ast::Object {
         ast::Object {
-            attributes: vec![ast::Attribute::struct_layout("Sequential")],
+            doc: None,
+            attributes: std::iter::once(ast::Attribute::struct_layout("Sequential"))
+                .chain(config.generated_code_attribute())
+                .collect(),
             object_type: ast::ObjectType::Struct,
             is_static: false,
             name: "SliceAbi".into(),
+            interfaces: Vec::new(),
             methods: Vec::new(),
             fields: vec![
                 ast::Field {
+                    attributes: Vec::new(),
                     name: "Ptr".to_string(),
                     ty: ast::CSharpType::Struct {
                         name: ast::Ident::new("IntPtr"),
                     },
+                    const_value: None,
                 },
                 ast::Field {
+                    attributes: Vec::new(),
                     name: "Len".to_string(),
                     ty: ast::CSharpType::UInt64,
+                    const_value: None,
                 },
             ],
+            regions: Vec::new(),
+            is_partial: false,
         }
     }
 
-    fn top_level_methods_obj(methods: &[BindingMethod]) -> ast::Object {
-        ast::Object {
-            attributes: Vec::new(),
-            object_type: ast::ObjectType::Class,
-            is_static: true,
-            name: "TopLevelMethods".into(),
-            methods: methods.iter().flat_map(|m| m.to_ast_methods()).collect(),
+    /// A definition of DoesNotReturnAttribute, for the frameworks that predate it.
+    ///
+    /// The compiler's flow analysis only looks at the attribute's name, so an internal copy of it
+    /// works just as well as the real one.
+    fn does_not_return_polyfill() -> ast::RawLines {
+        ast::RawLines::dedented(
+            r#"
+            #if !NETSTANDARD2_1_OR_GREATER && !NETCOREAPP3_0_OR_GREATER
+            [AttributeUsage(AttributeTargets.Method, Inherited = false)]
+            internal sealed class DoesNotReturnAttribute : Attribute
+            {
+            }
+            #endif
+            "#,
+        )
+    }
+
+    /// A string encoded as null terminated UTF-8, into the caller's stack buffer when it fits or
+    /// into an array rented from the shared pool otherwise, to be returned when it's disposed of.
+    ///
+    /// The stack buffer is checked against the worst case encoded length first, so that the
+    /// string is only measured exactly when it might not fit. No length is passed alongside it,
+    /// as the thunks read `&str` arguments up to the null terminator just as they do the strings
+    /// marshalled as `LPUTF8Str`.
+    ///
+    /// Only uses APIs that netstandard2.0 has, with `System.Memory` providing the spans.
+    fn utf8_buffer_obj(config: &CodegenConfig) -> ast::RawLines {
+        let mut obj = ast::RawLines::dedented(
+            r#"
+            public unsafe ref struct Utf8Buffer
+            {
+                public const Int32 StackLength = 256;
+
+                private readonly Span<Byte> _bytes;
+                private Byte[] _rented;
+
+                public Utf8Buffer(string value, Span<Byte> stackBuffer)
+                {
+                    if (value == null)
+                    {
+                        throw new ArgumentNullException(nameof(value));
+                    }
+
+                    Int32 length = System.Text.Encoding.UTF8.GetMaxByteCount(value.Length) + 1;
+                    if (length > stackBuffer.Length)
+                    {
+                        length = System.Text.Encoding.UTF8.GetByteCount(value) + 1;
+                    }
+
+                    if (length > stackBuffer.Length)
+                    {
+                        _rented = System.Buffers.ArrayPool<Byte>.Shared.Rent(length);
+                        _bytes = _rented;
+                    }
+                    else
+                    {
+                        _rented = null;
+                        _bytes = stackBuffer;
+                    }
+
+                    fixed (char* chars = value)
+                    fixed (Byte* bytes = _bytes)
+                    {
+                        bytes[System.Text.Encoding.UTF8.GetBytes(chars, value.Length, bytes, _bytes.Length)] = 0;
+                    }
+                }
+
+                public ref Byte GetPinnableReference() => ref _bytes.GetPinnableReference();
+
+                public void Dispose()
+                {
+                    if (_rented != null)
+                    {
+                        System.Buffers.ArrayPool<Byte>.Shared.Return(_rented);
+                        _rented = null;
+                    }
+                }
+            }
+            "#,
+        );
+
+        if let Some(attribute) = config.generated_code_attribute() {
+            let params: Vec<_> = attribute.positional_parameters.iter().map(|p| p.to_string()).collect();
+            obj.lines.insert(0, format!("[{}({})]", attribute.name, params.join(", ")));
+        }
+
+        obj
+    }
+
+    /// An attribute for marking methods whose return value shouldn't be ignored, for analyzers to
+    /// pick up on.
+    fn must_use_attribute_obj() -> ast::Object {
+        ast::Object {
+            doc: Some(ast::DocComment {
+                remarks: vec![
+                    "Marks a method whose return value should not be ignored, as its native counterpart is #[must_use].".to_string(),
+                ],
+            }),
+            attributes: vec![ast::Attribute {
+                name: "AttributeUsage".to_string(),
+                positional_parameters: vec![ast::LiteralValue::EnumValue(
+                    "AttributeTargets".to_string(),
+                    "Method".to_string(),
+                )],
+                named_parameters: Vec::new(),
+                condition: None,
+            }],
+            object_type: ast::ObjectType::Class,
+            is_static: false,
+            name: "MustUseAttribute".into(),
+            interfaces: vec!["Attribute".to_string()],
+            methods: Vec::new(),
+            fields: vec![ast::Field {
+                attributes: Vec::new(),
+                name: "Reason".to_string(),
+                ty: ast::CSharpType::String,
+                const_value: None,
+            }],
+            regions: Vec::new(),
+            is_partial: false,
+        }
+    }
+
+    fn top_level_methods_objs(name: &str, methods: &[BindingMethod], config: &CodegenConfig) -> Vec<ast::Object> {
+        // Attributes are merged across partial declarations, and GeneratedCode can't be repeated
+        let object = |methods, regions, is_partial, first: bool| ast::Object {
+            doc: None,
+            attributes: config.generated_code_attribute().filter(|_| first).into_iter().collect(),
+            object_type: ast::ObjectType::Class,
+            is_static: true,
+            name: name.into(),
+            interfaces: Vec::new(),
+            methods,
             fields: Vec::new(),
+            regions,
+            is_partial,
+        };
+
+        let externs = || methods.iter().map(|m| m.dll_imported_method()).collect();
+        let wrappers = || methods.iter().flat_map(|m| m.wrapper_methods()).collect();
+
+        if config.partial_members {
+            return vec![
+                object(externs(), Vec::new(), true, true),
+                object(wrappers(), Vec::new(), true, false),
+            ];
+        }
+
+        if config.regions {
+            let regions = vec![
+                ast::Region { name: "Native Methods".to_string(), methods: externs() },
+                ast::Region { name: "Public API".to_string(), methods: wrappers() },
+            ];
+            return vec![object(Vec::new(), regions, false, true)];
+        }
+
+        vec![object(methods.iter().flat_map(|m| m.to_ast_methods()).collect(), Vec::new(), false, true)]
+    }
+
+    fn file_comment(&self) -> ast::BlockComment {
+        let mut text = vec!["This is a generated file, do not modify by hand.".to_string()];
+
+        if self.config.header == FileHeader::Provenance {
+            let mut crates: Vec<_> = self.data.descriptors.iter()
+                .filter_map(|descriptor| match descriptor {
+                    core::BindgenExportDescriptor::Crate(c) => Some(format!("{} {}", c.name, c.version)),
+                    _ => None,
+                })
+                .collect();
+            crates.sort();
+            crates.dedup();
+
+            text.push(String::new());
+            text.push(format!(
+                "Generated by {} {} from {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION"),
+                crates.join(", "),
+            ));
+        }
+
+        ast::BlockComment { text }
+    }
+
+    /// The C# namespace that items defined in the given Rust module are generated into.
+    fn namespace_for(&self, module_path: &str) -> String {
+        if !self.config.module_namespaces {
+            return format!("{}Bindings", self.lib_name.to_camel_case());
+        }
+
+        self.config.namespace_root
+            .iter()
+            .cloned()
+            .chain(module_path.split("::").map(|segment| segment.to_camel_case()))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// The C# namespace that an item is generated into, given explicitly or picked from the Rust
+    /// module it was defined in.
+    fn item_namespace(&self, namespace: &Option<String>, module_path: &str) -> String {
+        namespace.clone().unwrap_or_else(|| self.namespace_for(module_path))
+    }
+
+    /// Whether any item was given a namespace explicitly, so that items in different namespaces
+    /// may refer to each other even when not splitting them by module.
+    fn has_explicit_namespaces(&self) -> bool {
+        self.data.descriptors.iter().any(|descriptor| match descriptor {
+            core::BindgenExportDescriptor::Function(f) => f.namespace.is_some(),
+            core::BindgenExportDescriptor::Struct(s) => s.namespace.is_some(),
+            core::BindgenExportDescriptor::Opaque(o) => o.namespace.is_some(),
+            core::BindgenExportDescriptor::Enum(e) => e.namespace.is_some(),
+            core::BindgenExportDescriptor::Crate(_) => false,
+        })
+    }
+
+    /// Whether any argument, return value, or struct field (or anything they refer to) has a type
+    /// matching the predicate.
+    fn uses_type(&self, pred: &dyn Fn(&core::BindgenTypeDescriptor) -> bool) -> bool {
+        self.data.descriptors.iter().any(|descriptor| match descriptor {
+            core::BindgenExportDescriptor::Function(f) => {
+                f.arguments.iter().any(|a| mentions_type(&a.ty, pred)) || mentions_type(&f.return_ty, pred)
+            }
+            core::BindgenExportDescriptor::Struct(s) => s.fields.iter().any(|f| mentions_type(&f.ty, pred)),
+            _ => false,
+        })
+    }
+
+    /// Aliases standing in for types missing from some of the frameworks the bindings may target.
+    fn using_aliases(&self) -> Vec<ast::UsingAlias> {
+        self.half_aliases(Some(NO_HALF_CONDITION))
+    }
+
+    /// Aliases for a separate project calling into the bindings.
+    ///
+    /// Whatever that project targets, it calls the netstandard2.0 build of the bindings, whose
+    /// signatures always take the aliased types. So the aliases must always apply, even where the
+    /// real type exists.
+    fn bindings_aliases(&self) -> Vec<ast::UsingAlias> {
+        self.half_aliases(None)
+    }
+
+    fn half_aliases(&self, condition: Option<&str>) -> Vec<ast::UsingAlias> {
+        let mut aliases = Vec::new();
+        if self.uses_type(&|ty| matches!(ty, core::BindgenTypeDescriptor::Float { width: 16 })) {
+            aliases.push(ast::UsingAlias {
+                alias: "Half".to_string(),
+                target: "System.UInt16".to_string(),
+                condition: condition.map(str::to_string),
+            });
         }
+
+        aliases
     }
 
-    fn form_ast(&self) -> ast::Root {
-        let mut objects = self.data.descriptors.iter()
+    /// Every bound function, paired with the namespace it is generated into.
+    fn top_level_methods(&self) -> Result<Vec<(String, BindingMethod)>, CodegenError> {
+        self.data.descriptors.iter()
             .filter_map(|descriptor| match descriptor {
-                core::BindgenExportDescriptor::Struct(s) => Some(s),
+                core::BindgenExportDescriptor::Function(f) => Some(f),
+                _ => None
+            })
+            .map(|descriptor| {
+                descriptor.error_enum
+                    .as_deref()
+                    .map_or(Ok(()), |error_enum| self.check_error_enum(error_enum))
+                    .and_then(|_| BindingMethod::new(&self.lib_name, descriptor, self.config))
+                    .map(|m| (self.item_namespace(&descriptor.namespace, &descriptor.module_path), m))
+                    .map_err(CodegenError::for_item(&descriptor.real_name))
+            })
+            .collect()
+    }
+
+    /// Checks that the named enum is bound, and that every i32 can be converted to it unchanged.
+    fn check_error_enum(&self, name: &str) -> Result<(), &'static str> {
+        let error_enum = self.data.descriptors.iter()
+            .find_map(|descriptor| match descriptor {
+                core::BindgenExportDescriptor::Enum(e) if e.name == name => Some(e),
                 _ => None,
             })
-            .map(|descriptor| BindingStruct::new(descriptor))
-            .map(|s| s.map(|s| Box::new(s.to_ast_object()) as Box<dyn ast::AstNode>))
-            .collect::<Result<Vec<_>, _>>().expect("Failed to process struct");
+            .ok_or("The error_enum of a function must name a bound enum")?;
+
+        if !error_enum.c_like || *error_enum.repr != (core::BindgenTypeDescriptor::Int { width: 32, signed: true }) {
+            return Err("The error_enum of a function must be a C-like enum with #[repr(i32)]");
+        }
 
-        let top_level_methods = self.data.descriptors.iter()
+        Ok(())
+    }
+
+    /// Using statements for System and each of the bindings namespaces, for a separate project
+    /// calling into the bindings, whose argument and return types may come from any of them.
+    fn bindings_usings(&self) -> Vec<ast::UsingStatement> {
+        let using = |path: &str| ast::UsingStatement { path: path.into() };
+
+        let mut using_statements = vec![using("System")];
+        using_statements.extend(self.data.descriptors.iter()
             .filter_map(|descriptor| match descriptor {
-                core::BindgenExportDescriptor::Function(f) => Some(f),
-                _ => None
+                core::BindgenExportDescriptor::Function(f) => Some((&f.namespace, &f.module_path)),
+                core::BindgenExportDescriptor::Struct(s) => Some((&s.namespace, &s.module_path)),
+                core::BindgenExportDescriptor::Opaque(o) => Some((&o.namespace, &o.module_path)),
+                core::BindgenExportDescriptor::Enum(e) => Some((&e.namespace, &e.module_path)),
+                core::BindgenExportDescriptor::Crate(_) => None,
             })
-            .map(|descriptor| BindingMethod::new(&self.lib_name, descriptor))
-            .collect::<Result<Vec<_>, _>>().expect("Failed to process method");
+            .map(|(namespace, module_path)| using(&self.item_namespace(namespace, module_path))));
 
-        objects.push(Box::new(CodegenInfo::slice_abi_obj()) as Box<dyn ast::AstNode>);
-        objects.push(Box::new(CodegenInfo::top_level_methods_obj(&top_level_methods)) as Box<dyn ast::AstNode>);
+        using_statements
+    }
 
-        ast::Root {
-            file_comment: Some(ast::BlockComment {
-                text: vec!["This is a generated file, do not modify by hand.".into()],
-            }),
-            using_statements: vec![
-                ast::UsingStatement {
-                    path: "System".into(),
-                },
-                ast::UsingStatement {
-                    path: "System.Runtime.InteropServices".into(),
-                },
-            ],
+    /// An xUnit test for each of the functions marked with `#[dotnet_bindgen(roundtrip)]`,
+    /// checking that edge case values survive being passed to and returned from native code.
+    ///
+    /// A mistake in how a type is marshalled (eg a bool marshalled as 4 bytes instead of 1) then
+    /// shows up as a failing test.
+    fn form_roundtrip_test_ast(&self) -> Result<ast::Root, CodegenError> {
+        let tests: Vec<_> = self.top_level_methods()?
+            .iter()
+            .filter_map(|(namespace, m)| m.roundtrip_values.as_ref().map(|values| {
+                m.roundtrip_test_method(&format!("global::{}.{}", namespace, m.class_name), values)
+            }))
+            .collect();
+
+        if tests.is_empty() {
+            eprintln!("warning: no functions are marked with #[dotnet_bindgen(roundtrip)], so the round trip test is empty");
+        }
+
+        let mut using_statements = self.bindings_usings();
+        using_statements.push(ast::UsingStatement { path: "Xunit".into() });
+
+        Ok(ast::Root {
+            file_comment: Some(self.file_comment()),
+            using_statements,
+            using_aliases: self.bindings_aliases(),
+            max_width: self.config.max_width,
             children: vec![Box::new(ast::Namespace {
-                name: format!("{}Bindings", self.lib_name.to_camel_case()),
-                children: objects,
+                name: format!("{}RoundTripTests", self.lib_name.to_camel_case()),
+                children: vec![Box::new(ast::Object {
+                    doc: None,
+                    attributes: Vec::new(),
+                    object_type: ast::ObjectType::Class,
+                    is_static: false,
+                    name: "RoundTripTests".into(),
+                    interfaces: Vec::new(),
+                    methods: tests,
+                    fields: Vec::new(),
+                    regions: Vec::new(),
+                    is_partial: false,
+                })],
             })],
+        })
+    }
+
+    /// A BenchmarkDotNet harness with a benchmark for each of the functions marked with
+    /// `#[dotnet_bindgen(bench)]`, or for every function if none are.
+    fn form_bench_ast(&self) -> Result<ast::Root, CodegenError> {
+        let methods = self.top_level_methods()?;
+        let any_marked = methods.iter().any(|(_, m)| m.bench);
+
+        let benchmarks = methods
+            .iter()
+            // Calling a diverging function would only ever end the benchmark run
+            .filter(|(_, m)| m.bench || (!any_marked && !m.diverges))
+            .map(|(namespace, m)| m.bench_method(&format!("global::{}.{}", namespace, m.class_name)))
+            .collect();
+
+        let program = ast::Method {
+            doc: None,
+            attributes: Vec::new(),
+            visibility: ast::Visibility::Public,
+            is_static: true,
+            is_extern: false,
+            is_unsafe: false,
+            is_override: false,
+            name: "Main".to_string(),
+            return_ty: ast::CSharpType::Void,
+            args: vec![ast::MethodArgument {
+                name: "args".into(),
+                ty: ast::CSharpType::Array { elem_type: Box::new(ast::CSharpType::String) },
+                direction: ast::Direction::ByValue,
+                is_this: false,
+                marshal_as: None,
+            }],
+            body: Some(vec![Box::new(ast::RawLines {
+                lines: vec!["BenchmarkSwitcher.FromAssembly(typeof(Program).Assembly).Run(args);".to_string()],
+            })]),
+        };
+
+        let object = |name: &str, is_static, methods| -> Box<dyn ast::AstNode> {
+            Box::new(ast::Object {
+                doc: None,
+                attributes: Vec::new(),
+                object_type: ast::ObjectType::Class,
+                is_static,
+                name: name.into(),
+                interfaces: Vec::new(),
+                methods,
+                fields: Vec::new(),
+                regions: Vec::new(),
+                is_partial: false,
+            })
+        };
+
+        let mut using_statements = self.bindings_usings();
+        using_statements.push(ast::UsingStatement { path: "BenchmarkDotNet.Attributes".into() });
+        using_statements.push(ast::UsingStatement { path: "BenchmarkDotNet.Running".into() });
+
+        Ok(ast::Root {
+            file_comment: Some(self.file_comment()),
+            using_statements,
+            using_aliases: self.bindings_aliases(),
+            max_width: self.config.max_width,
+            children: vec![Box::new(ast::Namespace {
+                name: format!("{}Benchmarks", self.lib_name.to_camel_case()),
+                children: vec![
+                    object("Benchmarks", false, benchmarks),
+                    object("Program", true, vec![program]),
+                ],
+            })],
+        })
+    }
+
+    fn form_ast(&self) -> Result<ast::Root, CodegenError> {
+        let mut objects: Vec<(String, Box<dyn ast::AstNode>)> = self.data.descriptors.iter()
+            .filter_map(|descriptor| match descriptor {
+                core::BindgenExportDescriptor::Struct(s) => Some(s),
+                _ => None,
+            })
+            .map(|descriptor| BindingStruct::new(descriptor).map_err(CodegenError::for_item(&descriptor.name)).map(|s| {
+                let namespace = self.item_namespace(&descriptor.namespace, &descriptor.module_path);
+                let mut objects = vec![(namespace.clone(), Box::new(s.to_ast_object(self.config)) as Box<dyn ast::AstNode>)];
+                if s.native_marshalling {
+                    objects.push((namespace, s.marshaller_object()));
+                }
+                objects
+            }))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        let top_level_methods = self.top_level_methods()?;
+
+        let handles: Vec<_> = self.data.descriptors.iter()
+            .filter_map(|descriptor| match descriptor {
+                core::BindgenExportDescriptor::Opaque(o) => Some(o),
+                _ => None,
+            })
+            .map(|descriptor| {
+                let handle_namespace = self.item_namespace(&descriptor.namespace, &descriptor.module_path);
+                let async_release = top_level_methods
+                    .iter()
+                    .filter(|_| self.config.async_dispose)
+                    .find(|(_, m)| m.released_handle() == Some(descriptor.name.as_str()))
+                    .map(|(namespace, m)| match self.config.module_namespaces || *namespace != handle_namespace {
+                        // Each namespace gets its own class of methods, so name the right one
+                        true => format!("global::{}.{}.{}", namespace, m.class_name, m.cs_name),
+                        false => format!("{}.{}", m.class_name, m.cs_name),
+                    });
+                (handle_namespace, BindingHandle::new(descriptor, async_release))
+            })
+            .collect();
+        let any_async_dispose = handles.iter().any(|(_, h)| h.async_release.is_some());
+        objects.extend(handles
+            .iter()
+            .map(|(namespace, handle)| (namespace.clone(), Box::new(handle.to_ast_object(self.config)) as Box<dyn ast::AstNode>)));
+
+        let enums = self.data.descriptors.iter()
+            .filter_map(|descriptor| match descriptor {
+                core::BindgenExportDescriptor::Enum(e) => Some(e),
+                _ => None,
+            })
+            .map(|descriptor| BindingEnum::new(descriptor)
+                .map(|e| (self.item_namespace(&descriptor.namespace, &descriptor.module_path), e.to_ast_node(self.config)))
+                .map_err(CodegenError::for_item(&descriptor.name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        objects.extend(enums);
+
+        objects.push((
+            self.namespace_for(&self.lib_name),
+            Box::new(CodegenInfo::slice_abi_obj(self.config)) as Box<dyn ast::AstNode>,
+        ));
+
+        if self.config.must_use_attribute && top_level_methods.iter().any(|(_, m)| m.must_use.is_some()) {
+            objects.push((
+                self.namespace_for(&self.lib_name),
+                Box::new(CodegenInfo::must_use_attribute_obj()) as Box<dyn ast::AstNode>,
+            ));
+        }
+
+        if top_level_methods.iter().any(|(_, m)| m.uses_utf8_buffer()) {
+            objects.push((
+                self.namespace_for(&self.lib_name),
+                Box::new(CodegenInfo::utf8_buffer_obj(self.config)) as Box<dyn ast::AstNode>,
+            ));
+        }
+
+        let any_diverging = top_level_methods.iter().any(|(_, m)| m.diverges);
+        if any_diverging {
+            objects.push((
+                "System.Diagnostics.CodeAnalysis".to_string(),
+                Box::new(CodegenInfo::does_not_return_polyfill()) as Box<dyn ast::AstNode>,
+            ));
         }
+
+        let mut methods_by_class: BTreeMap<(String, String), Vec<BindingMethod>> = BTreeMap::new();
+        for (namespace, method) in top_level_methods {
+            methods_by_class.entry((namespace, method.class_name.clone())).or_default().push(method);
+        }
+        for ((namespace, class_name), methods) in &methods_by_class {
+            for object in CodegenInfo::top_level_methods_objs(class_name, methods, self.config) {
+                objects.push((namespace.clone(), Box::new(object) as Box<dyn ast::AstNode>));
+            }
+        }
+
+        let mut namespaces: BTreeMap<String, Vec<Box<dyn ast::AstNode>>> = BTreeMap::new();
+        for (namespace, object) in objects {
+            namespaces.entry(namespace).or_default().push(object);
+        }
+
+        let mut using_statements = vec![
+            ast::UsingStatement {
+                path: "System".into(),
+            },
+            ast::UsingStatement {
+                path: "System.Runtime.InteropServices".into(),
+            },
+        ];
+        if any_async_dispose {
+            using_statements.push(ast::UsingStatement {
+                path: "System.Threading.Tasks".into(),
+            });
+        }
+        let import_namespaces = self.config.module_namespaces || self.has_explicit_namespaces();
+        if any_diverging && !import_namespaces {
+            using_statements.push(ast::UsingStatement {
+                path: "System.Diagnostics.CodeAnalysis".into(),
+            });
+        }
+        if import_namespaces {
+            // Items in one module may refer to types from any other
+            using_statements.extend(namespaces.keys().map(|namespace| ast::UsingStatement {
+                path: namespace.clone(),
+            }));
+        }
+
+        Ok(ast::Root {
+            file_comment: Some(self.file_comment()),
+            using_statements,
+            using_aliases: self.using_aliases(),
+            max_width: self.config.max_width,
+            children: namespaces
+                .into_iter()
+                .map(|(name, children)| Box::new(ast::Namespace { name, children }) as Box<dyn ast::AstNode>)
+                .collect(),
+        })
+    }
+}
+
+/// The name and version of each nuget package the generated bindings depend on.
+///
+/// These provide the parts of newer frameworks that the generated source needs, as the project
+/// targets netstandard2.0.
+pub fn package_references(data: &BindgenData, config: &CodegenConfig) -> Vec<(String, String)> {
+    let functions = || data.descriptors.iter().filter_map(|descriptor| match descriptor {
+        core::BindgenExportDescriptor::Function(f) => Some(f),
+        _ => None,
+    });
+
+    let mut references = Vec::new();
+
+    let has_out_buffer = functions().flat_map(|f| &f.arguments).any(|a| matches!(
+        &a.ty,
+        core::BindgenTypeDescriptor::Ref { target, .. }
+            if matches!(**target, core::BindgenTypeDescriptor::Array { .. })
+    ));
+    let lib_name = data.source_file.bin_base_name();
+    let has_utf8_buffer = functions()
+        .filter_map(|f| BindingMethod::new(&lib_name, f, config).ok())
+        .any(|m| m.uses_utf8_buffer());
+    if has_out_buffer || has_utf8_buffer {
+        references.push(("System.Memory".to_string(), "4.5.4".to_string()));
+    }
+
+    if config.async_dispose && functions().any(|f| f.release) {
+        references.push(("Microsoft.Bcl.AsyncInterfaces".to_string(), "1.1.1".to_string()));
     }
+
+    references
+}
+
+/// Things about the bound items which are likely mistakes, but don't stop bindings being generated.
+pub fn warnings(data: &BindgenData) -> Vec<String> {
+    data.descriptors
+        .iter()
+        .filter_map(|descriptor| match descriptor {
+            core::BindgenExportDescriptor::Function(f) => Some(f),
+            _ => None,
+        })
+        .filter(|f| f.body.as_ref().is_some_and(|body| !body.contains(&f.thunk_name)))
+        .map(|f| format!("the custom body of {} never calls the {} extern", f.real_name, f.thunk_name))
+        .collect()
 }
 
-pub fn form_ast_from_data(data: &BindgenData) -> ast::Root {
-    let info = CodegenInfo::new(data);
+pub fn form_ast_from_data(data: &BindgenData, config: &CodegenConfig) -> Result<ast::Root, CodegenError> {
+    let info = CodegenInfo::new(data, config);
     info.form_ast()
-}
\ No newline at end of file
+}
+
+pub fn form_bench_ast_from_data(data: &BindgenData, config: &CodegenConfig) -> Result<ast::Root, CodegenError> {
+    let info = CodegenInfo::new(data, config);
+    info.form_bench_ast()
+}
+
+pub fn form_roundtrip_test_ast_from_data(data: &BindgenData, config: &CodegenConfig) -> Result<ast::Root, CodegenError> {
+    let info = CodegenInfo::new(data, config);
+    info.form_roundtrip_test_ast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: [CharSet; 3] = [CharSet::Utf8, CharSet::Ansi, CharSet::Unicode];
+
+    // The (wide, utf8) flags of each string descriptor
+    const STR: (bool, bool) = (false, true);
+    const C_STR: (bool, bool) = (false, false);
+    const WIDE_STR: (bool, bool) = (true, false);
+
+    fn marshal_as(
+        default: CharSet,
+        explicit: Option<CharSet>,
+        (wide, utf8): (bool, bool),
+    ) -> Result<&'static str, &'static str> {
+        CharSet::marshal_as(default, explicit, wide, utf8)
+    }
+
+    #[test]
+    fn str_is_always_utf8() {
+        for default in DEFAULTS {
+            assert_eq!(marshal_as(default, None, STR), Ok("LPUTF8Str"));
+            assert_eq!(marshal_as(default, Some(CharSet::Utf8), STR), Ok("LPUTF8Str"));
+            assert!(marshal_as(default, Some(CharSet::Ansi), STR).is_err());
+            assert!(marshal_as(default, Some(CharSet::Unicode), STR).is_err());
+        }
+    }
+
+    #[test]
+    fn c_str_follows_the_narrow_charset() {
+        assert_eq!(marshal_as(CharSet::Utf8, None, C_STR), Ok("LPUTF8Str"));
+        assert_eq!(marshal_as(CharSet::Ansi, None, C_STR), Ok("LPStr"));
+        assert_eq!(marshal_as(CharSet::Unicode, None, C_STR), Ok("LPUTF8Str"));
+
+        for default in DEFAULTS {
+            assert_eq!(marshal_as(default, Some(CharSet::Utf8), C_STR), Ok("LPUTF8Str"));
+            assert_eq!(marshal_as(default, Some(CharSet::Ansi), C_STR), Ok("LPStr"));
+            assert!(marshal_as(default, Some(CharSet::Unicode), C_STR).is_err());
+        }
+    }
+
+    #[test]
+    fn wide_str_is_always_utf16() {
+        for default in DEFAULTS {
+            assert_eq!(marshal_as(default, None, WIDE_STR), Ok("LPWStr"));
+            assert_eq!(marshal_as(default, Some(CharSet::Unicode), WIDE_STR), Ok("LPWStr"));
+            assert!(marshal_as(default, Some(CharSet::Utf8), WIDE_STR).is_err());
+            assert!(marshal_as(default, Some(CharSet::Ansi), WIDE_STR).is_err());
+        }
+    }
+
+    #[test]
+    fn lang_version_round_trips_through_the_csproj_value() {
+        for version in ["latest", "7.3", "12.0"] {
+            assert_eq!(version.parse::<LangVersion>().unwrap().to_string(), version);
+        }
+        assert_eq!("12".parse::<LangVersion>().unwrap().to_string(), "12.0");
+    }
+
+    fn function(arguments: Vec<core::BindgenTypeDescriptor>) -> core::BindgenFunctionDescriptor {
+        let arguments = arguments.into_iter().map(|ty| ("arg", ty)).collect();
+        named_function("f", arguments, core::BindgenTypeDescriptor::Void)
+    }
+
+    fn named_function(
+        name: &str,
+        arguments: Vec<(&str, core::BindgenTypeDescriptor)>,
+        return_ty: core::BindgenTypeDescriptor,
+    ) -> core::BindgenFunctionDescriptor {
+        core::BindgenFunctionDescriptor {
+            real_name: name.to_string(),
+            thunk_name: format!("__bindgen_thunk_{}", name),
+            arguments: arguments
+                .into_iter()
+                .map(|(name, ty)| core::BindgenFunctionArgumentDescriptor {
+                    name: name.to_string(),
+                    ty,
+                    out: false,
+                    charset: None,
+                })
+                .collect(),
+            return_ty,
+            cfg: None,
+            platforms: Vec::new(),
+            no_try: false,
+            body: None,
+            release: false,
+            bench: false,
+            roundtrip: false,
+            must_use: None,
+            diverges: false,
+            error_enum: None,
+            library: None,
+            namespace: None,
+            class: None,
+            module_path: "test_lib".to_string(),
+        }
+    }
+
+    fn device_info() -> core::BindgenTypeDescriptor {
+        let field = |name: &str, ty, string_buffer| core::BindgenStructFieldDescriptor {
+            name: name.to_string(),
+            ty,
+            string_buffer,
+        };
+
+        core::BindgenTypeDescriptor::Struct(core::BindgenStructDescriptor {
+            name: "DeviceInfo".to_string(),
+            fields: vec![
+                field("id", core::BindgenTypeDescriptor::Int { width: 32, signed: false }, false),
+                field(
+                    "name",
+                    core::BindgenTypeDescriptor::Array {
+                        elem_type: Box::new(core::BindgenTypeDescriptor::Int { width: 8, signed: false }),
+                        len: 32,
+                    },
+                    true,
+                ),
+            ],
+            cfg: None,
+            native_marshalling: false,
+            as_type: None,
+            namespace: None,
+            module_path: "test_lib".to_string(),
+        })
+    }
+
+    #[test]
+    fn string_buffer_structs_are_only_passed_alone() {
+        use dotnet_bindgen_core::BindgenTypeDescriptor as Desc;
+
+        let config = CodegenConfig::default();
+        let method = |ty| BindingMethod::new("test_lib", &function(vec![ty]), &config);
+
+        assert!(method(device_info()).is_ok());
+        assert!(method(Desc::Ref { target: Box::new(device_info()), mutable: false }).is_ok());
+
+        let slice = Desc::Slice { elem_type: Box::new(device_info()) };
+        let array = Desc::Array { elem_type: Box::new(device_info()), len: 4 };
+        let out_buffer = Desc::Ref { target: Box::new(array.clone()), mutable: true };
+        for ty in [slice, array, out_buffer] {
+            assert!(method(ty).is_err());
+        }
+    }
+
+    #[test]
+    fn errors_name_the_item() {
+        let data = BindgenData {
+            source_file: "libtest_lib.so".into(),
+            descriptors: vec![core::BindgenExportDescriptor::Enum(core::BindgenEnumDescriptor {
+                name: "Handedness".to_string(),
+                repr: Box::new(core::BindgenTypeDescriptor::Size { signed: false }),
+                variants: vec![core::BindgenEnumVariantDescriptor {
+                    name: "Left".to_string(),
+                    discriminant: 0,
+                }],
+                c_like: true,
+                cfg: None,
+                namespace: None,
+                module_path: "test_lib".to_string(),
+            })],
+        };
+
+        let err = form_ast_from_data(&data, &CodegenConfig::default()).err().unwrap();
+        assert_eq!(err.item, "Handedness");
+        assert_eq!(
+            err.to_string(),
+            "Can't generate bindings for Handedness: C-like enums must be represented by a fixed width integer type"
+        );
+    }
+
+    /// The generated bindings of the given items, as C# source
+    fn render(descriptors: Vec<core::BindgenExportDescriptor>, config: &CodegenConfig) -> String {
+        let data = BindgenData { source_file: "libtest_lib.so".into(), descriptors };
+        let mut out = Vec::new();
+        form_ast_from_data(&data, config).unwrap().render(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn render_functions(functions: Vec<core::BindgenFunctionDescriptor>, config: &CodegenConfig) -> String {
+        render(functions.into_iter().map(core::BindgenExportDescriptor::Function).collect(), config)
+    }
+
+    fn int(width: u8, signed: bool) -> core::BindgenTypeDescriptor {
+        core::BindgenTypeDescriptor::Int { width, signed }
+    }
+
+    fn optional_ref(target: core::BindgenTypeDescriptor) -> core::BindgenTypeDescriptor {
+        core::BindgenTypeDescriptor::NullableRef { target: Box::new(target) }
+    }
+
+    #[test]
+    fn custom_bodies_replace_only_the_forwarding_call() {
+        let arguments = vec![("value", optional_ref(int(32, true))), ("by", int(16, true))];
+        let mut custom = named_function("scale", arguments, int(32, true));
+        custom.body = Some("return __bindgen_thunk_scale(value, by);".to_string());
+        custom.platforms = vec!["linux".to_string()];
+
+        let config = CodegenConfig {
+            optional_ref_overloads: true,
+            widen_int_args: true,
+            ..CodegenConfig::default()
+        };
+        let bindings = render_functions(vec![custom], &config);
+
+        assert!(bindings.contains("private static extern Int32 __bindgen_thunk_scale(IntPtr value, Int16 by);"));
+        assert!(bindings.contains(concat!(
+            "        public static Int32 Scale(IntPtr value, Int16 by)\n",
+            "        {\n",
+            "            if (!(RuntimeInformation.IsOSPlatform(OSPlatform.Linux)))\n",
+            "            {\n",
+            "                throw new PlatformNotSupportedException(\"Scale is only supported on: linux\");\n",
+            "            }\n",
+            "            return __bindgen_thunk_scale(value, by);\n",
+            "        }\n",
+        )));
+        assert_eq!(bindings.matches("public static Int32 Scale(").count(), 1);
+    }
+
+    #[test]
+    fn custom_bodies_not_calling_the_extern_are_warned_about() {
+        let mut calls = named_function("calls", Vec::new(), core::BindgenTypeDescriptor::Void);
+        calls.body = Some("__bindgen_thunk_calls();".to_string());
+        let mut ignores = named_function("ignores", Vec::new(), core::BindgenTypeDescriptor::Void);
+        ignores.body = Some("return;".to_string());
+
+        let data = BindgenData {
+            source_file: "libtest_lib.so".into(),
+            descriptors: vec![
+                core::BindgenExportDescriptor::Function(calls),
+                core::BindgenExportDescriptor::Function(ignores),
+            ],
+        };
+        assert_eq!(
+            warnings(&data),
+            vec!["the custom body of ignores never calls the __bindgen_thunk_ignores extern".to_string()]
+        );
+    }
+
+    #[test]
+    fn optional_refs_get_overloads_forwarding_to_one_extern() {
+        let config = CodegenConfig { optional_ref_overloads: true, ..CodegenConfig::default() };
+        let optional = named_function("lookup", vec![("key", optional_ref(int(32, true)))], int(32, true));
+        let mut custom = named_function("custom", vec![("key", optional_ref(int(32, true)))], int(32, true));
+        custom.body = Some("return __bindgen_thunk_custom(key);".to_string());
+
+        let bindings = render_functions(vec![optional, custom], &config);
+
+        assert_eq!(bindings.matches("static extern Int32 __bindgen_thunk_lookup(IntPtr key);").count(), 1);
+        assert!(bindings.contains(concat!(
+            "        public static Int32 Lookup(Int32 key)\n",
+            "        {\n",
+            "            IntPtr _gen0;\n",
+            "            unsafe\n",
+            "            {\n",
+            "                _gen0 = (IntPtr)(&(key));\n",
+            "                return __bindgen_thunk_lookup(_gen0);\n",
+            "            }\n",
+            "        }\n",
+        )));
+        assert!(bindings.contains(concat!(
+            "        public static Int32 Lookup()\n",
+            "        {\n",
+            "            return __bindgen_thunk_lookup(IntPtr.Zero);\n",
+            "        }\n",
+        )));
+
+        // A custom body is written against the extern's own signature, so isn't overloaded
+        assert_eq!(bindings.matches("public static Int32 Custom(").count(), 1);
+        assert!(bindings.contains("public static Int32 Custom(IntPtr key)"));
+    }
+
+    #[test]
+    fn bools_are_marshalled_as_one_byte() {
+        let bool_ty = core::BindgenTypeDescriptor::Bool;
+        let bindings = render_functions(
+            vec![named_function("negate", vec![("value", bool_ty.clone())], bool_ty)],
+            &CodegenConfig::default(),
+        );
+
+        assert!(bindings.contains(concat!(
+            "        [DllImport(\"test_lib\", EntryPoint = \"__bindgen_thunk_negate\")]\n",
+            "        [return: MarshalAs(UnmanagedType.I1)]\n",
+            "        private static extern bool __bindgen_thunk_negate([MarshalAs(UnmanagedType.I1)] bool value);\n",
+        )));
+        assert!(bindings.contains("public static bool Negate(bool value)"));
+    }
+
+    #[test]
+    fn only_methods_with_pointers_in_their_signature_are_unsafe() {
+        let buffer = core::BindgenTypeDescriptor::Array { elem_type: Box::new(int(8, false)), len: 16 };
+        let mut fill = named_function(
+            "fill",
+            vec![("buf", core::BindgenTypeDescriptor::Ref { target: Box::new(buffer), mutable: true })],
+            core::BindgenTypeDescriptor::Void,
+        );
+        fill.arguments[0].out = true;
+
+        let bindings = render_functions(vec![fill], &CodegenConfig::default());
+
+        // The extern takes a pointer, but the wrapper only uses one inside its unsafe block
+        assert!(bindings.contains("private static extern unsafe void __bindgen_thunk_fill(Byte* buf);"));
+        assert!(bindings.contains("public static Byte[] Fill()\n"));
+        assert!(bindings.contains("            unsafe\n"));
+
+        let arg = |ty| ast::MethodArgument {
+            name: "arg".into(),
+            ty,
+            direction: ast::Direction::ByValue,
+            is_this: false,
+            marshal_as: None,
+        };
+        let byte_ptr = || ast::CSharpType::Ptr { target: Box::new(ast::CSharpType::Byte) };
+        assert!(!signature_is_unsafe(&[arg(ast::CSharpType::intptr())], &ast::CSharpType::Void));
+        assert!(signature_is_unsafe(&[arg(byte_ptr())], &ast::CSharpType::Void));
+        assert!(signature_is_unsafe(&[], &byte_ptr()));
+    }
+
+    #[test]
+    fn integer_widening_matrix() {
+        let config = CodegenConfig {
+            widen_int_args: true,
+            widen_unsigned_returns: true,
+            ..CodegenConfig::default()
+        };
+
+        // (width, signed, native type, widened argument type, returned type)
+        let cases = [
+            (8, true, "SByte", "Int64", "SByte"),
+            (8, false, "Byte", "UInt64", "Int16"),
+            (16, true, "Int16", "Int64", "Int16"),
+            (16, false, "UInt16", "UInt64", "Int32"),
+            (32, true, "Int32", "Int64", "Int32"),
+            (32, false, "UInt32", "UInt64", "Int64"),
+            (64, true, "Int64", "Int64", "Int64"),
+            (64, false, "UInt64", "UInt64", "UInt64"),
+        ];
+
+        for (width, signed, native, widened, returned) in cases {
+            let ty = int(width, signed);
+            let bindings = render_functions(vec![named_function("f", vec![("value", ty.clone())], ty)], &config);
+
+            assert!(bindings.contains(&format!("public static {} F({} value)", returned, widened)), "{}", native);
+
+            // Narrowing a widened argument back down can overflow, so is checked
+            let narrowed = bindings.contains(&format!("checked(({})(value))", native));
+            assert_eq!(narrowed, width < 64, "{}", native);
+
+            // Widening an unsigned return value never can, so isn't
+            let lossless = bindings.contains(&format!("return unchecked(({})(__bindgen_thunk_f(", returned));
+            assert_eq!(lossless, !signed && width < 64, "{}", native);
+        }
+    }
+
+    #[test]
+    fn struct_fields_are_marshalled_like_arguments() {
+        let colour = core::BindgenEnumDescriptor {
+            name: "Colour".to_string(),
+            repr: Box::new(int(8, false)),
+            variants: vec![core::BindgenEnumVariantDescriptor { name: "Red".to_string(), discriminant: 1 }],
+            c_like: true,
+            cfg: None,
+            namespace: None,
+            module_path: "test_lib".to_string(),
+        };
+        let field = |name: &str, ty| core::BindgenStructFieldDescriptor {
+            name: name.to_string(),
+            ty,
+            string_buffer: false,
+        };
+        let pixel = core::BindgenStructDescriptor {
+            name: "Pixel".to_string(),
+            fields: vec![
+                field("visible", core::BindgenTypeDescriptor::Bool),
+                field("colour", core::BindgenTypeDescriptor::Enum(colour.clone())),
+                field("alpha", int(8, false)),
+            ],
+            cfg: None,
+            native_marshalling: false,
+            as_type: None,
+            namespace: None,
+            module_path: "test_lib".to_string(),
+        };
+
+        let bindings = render(
+            vec![core::BindgenExportDescriptor::Struct(pixel), core::BindgenExportDescriptor::Enum(colour)],
+            &CodegenConfig::default(),
+        );
+
+        assert!(bindings.contains(concat!(
+            "    public struct Pixel\n",
+            "    {\n",
+            "        [MarshalAs(UnmanagedType.I1)]\n",
+            "        public bool Visible;\n",
+            "        public Colour Colour;\n",
+            "        public Byte Alpha;\n",
+            "    }\n",
+        )));
+        assert!(bindings.contains("public enum Colour : byte"));
+    }
+
+    #[test]
+    fn pointer_arguments_cross_as_intptr() {
+        let buf = core::BindgenTypeDescriptor::Ptr { inner: Box::new(int(8, false)), mutable: false };
+        let write = named_function("write", vec![("buf", buf), ("len", int(64, false))], int(64, false));
+
+        let bindings = render_functions(vec![write], &CodegenConfig::default());
+
+        assert!(bindings.contains(concat!(
+            "        [DllImport(\"test_lib\", EntryPoint = \"__bindgen_thunk_write\")]\n",
+            "        private static extern UInt64 __bindgen_thunk_write(IntPtr buf, UInt64 len);\n",
+        )));
+        assert!(bindings.contains("public static UInt64 Write(IntPtr buf, UInt64 len)"));
+    }
+
+    #[test]
+    fn enums_over_128_bit_integers_are_rejected() {
+        let data = BindgenData {
+            source_file: "libtest_lib.so".into(),
+            descriptors: vec![core::BindgenExportDescriptor::Enum(core::BindgenEnumDescriptor {
+                name: "Flags".to_string(),
+                repr: Box::new(int(128, false)),
+                variants: vec![core::BindgenEnumVariantDescriptor {
+                    name: "All".to_string(),
+                    discriminant: 1,
+                }],
+                c_like: true,
+                cfg: None,
+                namespace: None,
+                module_path: "test_lib".to_string(),
+            })],
+        };
+
+        let err = form_ast_from_data(&data, &CodegenConfig::default()).err().unwrap();
+        assert_eq!(err.item, "Flags");
+        assert_eq!(err.to_string(), "Can't generate bindings for Flags: Unrecognized type");
+    }
+}
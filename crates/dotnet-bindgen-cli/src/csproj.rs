@@ -24,7 +24,7 @@ impl NativeBinary {
             .file_name()
             .expect("Expect a native binary path to have a filename")
             .to_str()
-            .expect("Expect a native binary filename to be valid unicode".into())
+            .expect("Expect a native binary filename to be valid unicode")
             .to_owned()
     }
 
@@ -83,8 +83,17 @@ impl NativeBinarySet {
 
 pub struct ProjFile {
     pub target_framework: String,
+
+    /// The C# version the project compiles with, which the generated source is written for
+    pub lang_version: String,
     pub allow_unsafe: bool,
     pub binary_set: NativeBinarySet,
+
+    /// The name and version of each nuget package the generated project depends on
+    pub package_references: Vec<(String, String)>,
+
+    /// Globs of source files under the project directory which aren't part of the project
+    pub excluded_sources: Vec<String>,
 }
 
 impl ProjFile {
@@ -92,13 +101,97 @@ impl ProjFile {
         format!(r#"<Project Sdk="Microsoft.NET.Sdk">
     <PropertyGroup>
         <TargetFramework>{}</TargetFramework>
+        <LangVersion>{}</LangVersion>
         <AllowUnsafeBlocks>{}</AllowUnsafeBlocks>
     </PropertyGroup>
-{}
+{}{}
 </Project>
 "#,
         self.target_framework,
+        self.lang_version,
         if self.allow_unsafe { "true" } else { "false" },
+        self.render_items_xml(),
         self.binary_set.render_proj_xml())
     }
-}
\ No newline at end of file
+
+    fn render_excluded_sources_xml(&self) -> String {
+        let mut xml_str = String::new();
+        for glob in &self.excluded_sources {
+            xml_str.push_str(&format!("        <Compile Remove=\"{}\" />\n", glob));
+        }
+
+        xml_str
+    }
+
+    /// The package references and excluded sources of the project, in a single ItemGroup
+    fn render_items_xml(&self) -> String {
+        if self.package_references.is_empty() && self.excluded_sources.is_empty() {
+            return String::new();
+        }
+
+        let mut xml_str = "    <ItemGroup>\n".to_string();
+        xml_str.push_str(&self.render_excluded_sources_xml());
+        for (name, version) in &self.package_references {
+            xml_str.push_str(&format!(
+                "        <PackageReference Include=\"{}\" Version=\"{}\" />\n",
+                name,
+                version,
+            ));
+        }
+        xml_str.push_str("    </ItemGroup>\n");
+
+        xml_str
+    }
+}
+/// A console project running a generated BenchmarkDotNet harness against the bindings project.
+pub struct BenchProjFile {
+    /// The path of the bindings project, relative to the benchmark project
+    pub bindings_proj_path: String,
+}
+
+impl BenchProjFile {
+    pub fn render_proj_xml(&self) -> String {
+        format!(r#"<Project Sdk="Microsoft.NET.Sdk">
+    <PropertyGroup>
+        <OutputType>Exe</OutputType>
+        <TargetFramework>net8.0</TargetFramework>
+        <Configuration>Release</Configuration>
+    </PropertyGroup>
+    <ItemGroup>
+        <PackageReference Include="BenchmarkDotNet" Version="0.13.12" />
+    </ItemGroup>
+    <ItemGroup>
+        <ProjectReference Include="{}" />
+    </ItemGroup>
+</Project>
+"#,
+        self.bindings_proj_path)
+    }
+}
+
+/// An xUnit project running the generated round trip test against the bindings project.
+pub struct TestProjFile {
+    /// The path of the bindings project, relative to the test project
+    pub bindings_proj_path: String,
+}
+
+impl TestProjFile {
+    pub fn render_proj_xml(&self) -> String {
+        format!(r#"<Project Sdk="Microsoft.NET.Sdk">
+    <PropertyGroup>
+        <TargetFramework>net8.0</TargetFramework>
+        <IsPackable>false</IsPackable>
+    </PropertyGroup>
+    <ItemGroup>
+        <PackageReference Include="Microsoft.NET.Test.Sdk" Version="17.8.0" />
+        <PackageReference Include="xunit" Version="2.6.2" />
+        <PackageReference Include="xunit.runner.visualstudio" Version="2.5.4" />
+    </ItemGroup>
+    <ItemGroup>
+        <ProjectReference Include="{}" />
+    </ItemGroup>
+</Project>
+"#,
+        self.bindings_proj_path)
+    }
+}
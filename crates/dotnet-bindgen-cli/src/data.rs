@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
@@ -45,9 +46,37 @@ impl BindgenData {
         self.descriptors.sort_by_cached_key(|d| match d {
             BindgenExportDescriptor::Function(f) => f.real_name.clone(),
             BindgenExportDescriptor::Struct(s) => s.name.clone(),
+            BindgenExportDescriptor::Opaque(o) => o.name.clone(),
+            BindgenExportDescriptor::Enum(e) => e.name.clone(),
+            BindgenExportDescriptor::Crate(c) => c.name.clone(),
         });
     }
 
+    /// Checks that no two functions are bound to the same native symbol, as they would otherwise
+    /// clash both when linking and as the EntryPoint of the generated extern methods.
+    ///
+    /// The macro only ever sees the single item it is expanding, so this can only be checked once
+    /// the descriptors of the whole binary have been collected.
+    pub fn validate_entry_points(&self) -> Result<(), String> {
+        let mut bound_by: HashMap<(Option<&str>, &str), &BindgenFunctionDescriptor> = HashMap::new();
+
+        for descriptor in &self.descriptors {
+            let f = match descriptor {
+                BindgenExportDescriptor::Function(f) => f,
+                _ => continue,
+            };
+
+            if let Some(first) = bound_by.insert((f.library.as_deref(), &f.thunk_name), f) {
+                return Err(format!(
+                    "`{}::{}` and `{}::{}` are both bound to the native symbol `{}`",
+                    first.module_path, first.real_name, f.module_path, f.real_name, f.thunk_name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn load(file_path: &Path) -> Result<Self, &'static str> {
         let mut fd = File::open(file_path).unwrap();
 
@@ -74,32 +74,52 @@ impl SourceBinarySpec {
 ///
 /// source_output_dir:
 ///     The root directory to write the source code of the generated project to.
+///
+/// codegen_config:
+///     Options controlling the shape of the generated bindings.
+///
+/// emit_bench:
+///     Also generate a BenchmarkDotNet harness for the bindings, as a separate project in a
+///     Benchmarks subdirectory.
+///
+/// emit_roundtrip_test:
+///     Also generate an xUnit test passing edge case values through each round trip function, as
+///     a separate project in a RoundTripTests subdirectory.
 fn generate_bindings(
     input_binaries: Vec<SourceBinarySpec>,
-    source_output_dir: &Path
-) -> Result<(), &'static str> {
+    source_output_dir: &Path,
+    codegen_config: &codegen::CodegenConfig,
+    emit_bench: bool,
+    emit_roundtrip_test: bool,
+) -> Result<(), String> {
     let base_name;
     // Basic validation of the given source binaries.
     match input_binaries.first() {
-        None => return Err("Must have at least one binary to generate bindings for"),
+        None => return Err("Must have at least one binary to generate bindings for".into()),
         Some(f) => {
             base_name = f.base_name.clone();
 
             if input_binaries.iter().any(|b| b.base_name != base_name) {
-                return Err("The given source binaries have different base names")
+                return Err("The given source binaries have different base names".into())
             }
 
             if input_binaries.iter()
                 .any(|b| b.bindgen_data.descriptors != f.bindgen_data.descriptors) {
-                return Err("The given source binaries expose different descriptors")
+                return Err("The given source binaries expose different descriptors".into())
             }
         }
     }
 
+    input_binaries.first().unwrap().bindgen_data.validate_entry_points()?;
+
+    for warning in codegen::warnings(&input_binaries.first().unwrap().bindgen_data) {
+        eprintln!("warning: {}", warning);
+    }
+
     // Ensure the output directory exists + is an empty directory
     if source_output_dir.exists() {
         if !source_output_dir.is_dir() {
-            return Err("The given source-output-dir is not a directory")
+            return Err("The given source-output-dir is not a directory".into())
         }
     } else {
         std::fs::create_dir_all(source_output_dir)
@@ -111,9 +131,23 @@ fn generate_bindings(
         .map_err(|_| "Failed to open the source output directory")?
         .any(|_| true)
     {
-        return Err("The given source-output-dir is not empty")
+        return Err("The given source-output-dir is not empty".into())
     }
 
+    // Form every ast before writing anything, so that an unbindable item doesn't leave behind a
+    // half generated project
+    let bindgen_data = &input_binaries.first().unwrap().bindgen_data;
+    let ast_root = codegen::form_ast_from_data(bindgen_data, codegen_config)
+        .map_err(|e| e.to_string())?;
+    let bench_root = match emit_bench {
+        true => Some(codegen::form_bench_ast_from_data(bindgen_data, codegen_config).map_err(|e| e.to_string())?),
+        false => None,
+    };
+    let roundtrip_test_root = match emit_roundtrip_test {
+        true => Some(codegen::form_roundtrip_test_ast_from_data(bindgen_data, codegen_config).map_err(|e| e.to_string())?),
+        false => None,
+    };
+
     // Generate + write the project file
     let binary_set = csproj::NativeBinarySet::new(
         input_binaries.iter().map(|b| csproj::NativeBinary::new(
@@ -122,10 +156,23 @@ fn generate_bindings(
         ))
     );
 
+    let package_references = codegen::package_references(bindgen_data, codegen_config);
+
+    let mut excluded_sources = Vec::new();
+    if bench_root.is_some() {
+        excluded_sources.push("Benchmarks/**".to_owned());
+    }
+    if roundtrip_test_root.is_some() {
+        excluded_sources.push("RoundTripTests/**".to_owned());
+    }
+
     let proj = csproj::ProjFile {
         target_framework: "netstandard2.0".to_owned(),
+        lang_version: codegen_config.lang_version.to_string(),
         allow_unsafe: true,
-        binary_set
+        binary_set,
+        package_references,
+        excluded_sources,
     };
 
     let proj_filename = format!("{}Bindings.csproj", base_name.to_camel_case());
@@ -135,22 +182,56 @@ fn generate_bindings(
     std::fs::write(proj_filepath, proj_content)
         .map_err(|_| "Failed to write csproj file")?;
 
-    // Generate binding source ast from one set of extracted data
-    // Write out a bindings source file from that ast
+    // Write out a bindings source file from the ast formed from one set of extracted data
     let bindings_filename = format!("{}Bindings.cs", base_name.to_camel_case());
     let bindings_filepath = source_output_dir.join(bindings_filename);
-    let mut bindings_file = std::fs::File::create(&bindings_filepath).expect(&format!(
-        "Can't open {} for writing",
-        bindings_filepath.to_str().unwrap()
-    ));
-    let ast_root = codegen::form_ast_from_data(&input_binaries.first().unwrap().bindgen_data);
+    let mut bindings_file = std::fs::File::create(&bindings_filepath)
+        .map_err(|_| format!("Can't open {} for writing", bindings_filepath.display()))?;
     ast_root.render(&mut bindings_file)
         .map_err(|_| "Failed to write bindings C# ast to file")?;
 
+    if let Some(bench_root) = bench_root {
+        let bench_dir = source_output_dir.join("Benchmarks");
+        std::fs::create_dir(&bench_dir)
+            .map_err(|_| "Failed to create benchmark output directory")?;
+
+        let bench_proj = csproj::BenchProjFile {
+            bindings_proj_path: format!("../{}Bindings.csproj", base_name.to_camel_case()),
+        };
+        let bench_proj_filepath = bench_dir.join(format!("{}Benchmarks.csproj", base_name.to_camel_case()));
+        std::fs::write(bench_proj_filepath, bench_proj.render_proj_xml())
+            .map_err(|_| "Failed to write benchmark csproj file")?;
+
+        let mut bench_file = std::fs::File::create(bench_dir.join("Benchmarks.cs"))
+            .map_err(|_| "Failed to open the benchmark source file for writing")?;
+        bench_root
+            .render(&mut bench_file)
+            .map_err(|_| "Failed to write benchmark C# ast to file")?;
+    }
+
+    if let Some(roundtrip_test_root) = roundtrip_test_root {
+        let test_dir = source_output_dir.join("RoundTripTests");
+        std::fs::create_dir(&test_dir)
+            .map_err(|_| "Failed to create round trip test output directory")?;
+
+        let test_proj = csproj::TestProjFile {
+            bindings_proj_path: format!("../{}Bindings.csproj", base_name.to_camel_case()),
+        };
+        let test_proj_filepath = test_dir.join(format!("{}RoundTripTests.csproj", base_name.to_camel_case()));
+        std::fs::write(test_proj_filepath, test_proj.render_proj_xml())
+            .map_err(|_| "Failed to write round trip test csproj file")?;
+
+        let mut test_file = std::fs::File::create(test_dir.join("RoundTripTests.cs"))
+            .map_err(|_| "Failed to open the round trip test source file for writing")?;
+        roundtrip_test_root
+            .render(&mut test_file)
+            .map_err(|_| "Failed to write round trip test C# ast to file")?;
+    }
+
     Ok(())
 }
 
-fn main() -> Result<(), &'static str> {
+fn main() -> Result<(), String> {
     let matches = App::new("dotnet-bindgen-cli tool")
         .author("Joe Roberts")
         .about("Extract binding data from annotated binaries + generate dotnet bindings")
@@ -167,6 +248,136 @@ fn main() -> Result<(), &'static str> {
             .value_name("Bin or Plat:Bin")
             .help("The path to the binary to process")
             .takes_value(true))
+        .arg(Arg::with_name("optional-ref-overloads")
+            .long("optional-ref-overloads")
+            .help(r#"Expose Option<&T> arguments through overloads that either take a T, or omit the
+    argument entirely, instead of as an IntPtr."#))
+        .arg(Arg::with_name("widen-int-args")
+            .long("widen-int-args")
+            .help("Have wrappers take integer arguments narrower than 64 bits as an Int64/UInt64"))
+        .arg(Arg::with_name("widen-unsigned-returns")
+            .long("widen-unsigned-returns")
+            .help("Have wrappers return unsigned integers narrower than 64 bits as the next wider signed type"))
+        .arg(Arg::with_name("length-overloads")
+            .long("length-overloads")
+            .help("Also generate overloads taking each usize/isize argument as an Int32 or Int64"))
+        .arg(Arg::with_name("struct-equality")
+            .long("struct-equality")
+            .help("Generate value based Equals/GetHashCode/==/!= members for each bound struct"))
+        .arg(Arg::with_name("try-pattern")
+            .long("try-pattern")
+            .help(r#"Expose functions returning a bool and taking trailing &mut T arguments as
+    `bool TryFoo(.., out T value)`, unless they list their own out(..) arguments or are marked
+    with #[dotnet_bindgen(no_try)]."#))
+        .arg(Arg::with_name("async-dispose")
+            .long("async-dispose")
+            .help(r#"Have each handle with a #[dotnet_bindgen(release)] function implement IAsyncDisposable,
+    freeing it on the thread pool."#))
+        .arg(Arg::with_name("module-namespaces")
+            .long("module-namespaces")
+            .help(r#"Generate each item into a namespace mirroring the Rust module it was defined in,
+    eg my_lib::geometry -> MyLib.Geometry"#))
+        .arg(Arg::with_name("namespace-root")
+            .long("namespace-root")
+            .value_name("Namespace")
+            .requires("module-namespaces")
+            .help("A namespace to prefix each of the module derived namespaces with")
+            .takes_value(true))
+        .arg(Arg::with_name("emit-bench")
+            .long("emit-bench")
+            .help(r#"Also generate a BenchmarkDotNet project with a benchmark for each function marked with
+    #[dotnet_bindgen(bench)], or for every function if none are."#))
+        .arg(Arg::with_name("emit-roundtrip-test")
+            .long("emit-roundtrip-test")
+            .help(r#"Also generate an xUnit project checking that edge case values survive a round trip
+    through each function marked with #[dotnet_bindgen(roundtrip)]."#))
+        .arg(Arg::with_name("must-use-attribute")
+            .long("must-use-attribute")
+            .help(r#"Mark the wrappers of #[must_use] functions with a generated [MustUse] attribute, as well
+    as noting it in their documentation."#))
+        .arg(Arg::with_name("regions")
+            .long("regions")
+            .help("Group the extern methods and their wrappers into separate #regions"))
+        .arg(Arg::with_name("emit-partial-members")
+            .long("emit-partial-members")
+            .conflicts_with("regions")
+            .help("Declare the extern methods and their wrappers in separate partial classes"))
+        .arg(Arg::with_name("extern-visibility")
+            .long("extern-visibility")
+            .value_name("public|internal|private")
+            .possible_values(&["public", "internal", "private"])
+            .default_value("private")
+            .help("The access modifier of the generated DllImport'd extern methods")
+            .takes_value(true))
+        .arg(Arg::with_name("wrapper-visibility")
+            .long("wrapper-visibility")
+            .value_name("public|internal")
+            .possible_values(&["public", "internal"])
+            .default_value("public")
+            .help("The access modifier of the idiomatic wrappers around the extern methods")
+            .takes_value(true))
+        .arg(Arg::with_name("max-width")
+            .long("max-width")
+            .value_name("Columns")
+            .help("Wrap method signatures and attribute arguments which would go beyond this column")
+            .takes_value(true))
+        .arg(Arg::with_name("header")
+            .long("header")
+            .value_name("minimal|provenance")
+            .possible_values(&["minimal", "provenance"])
+            .default_value("provenance")
+            .help(r#"What to write in the header comment of the generated source. provenance also names the
+    version of this tool, and the crates the bindings were generated from."#)
+            .takes_value(true))
+        .arg(Arg::with_name("charset")
+            .long("charset")
+            .value_name("utf8|ansi|unicode")
+            .possible_values(&["utf8", "ansi", "unicode"])
+            .default_value("utf8")
+            .help(r#"How string arguments are encoded by default. Only applies to strings whose Rust type
+    accepts it, &str arguments are always UTF-8 and WideStr arguments always UTF-16."#)
+            .takes_value(true))
+        .arg(Arg::with_name("narrowing")
+            .long("narrowing")
+            .value_name("checked|unchecked")
+            .possible_values(&["checked", "unchecked"])
+            .default_value("checked")
+            .help(r#"Whether widened integer arguments throw on overflow when narrowed back down, or
+    silently truncate."#)
+            .takes_value(true))
+        .arg(Arg::with_name("lang-version")
+            .long("lang-version")
+            .value_name("Version")
+            .default_value("latest")
+            .help(r#"The version of C# the generated source must compile with, eg "7.3", which is also
+    written into the generated csproj. Before 7.2 &T arguments are passed by ref rather than in."#)
+            .takes_value(true))
+        .arg(Arg::with_name("ref-readonly")
+            .long("ref-readonly")
+            .help("Have wrappers take &T arguments as ref readonly rather than in parameters, from C# 12"))
+        .arg(Arg::with_name("skip-locals-init")
+            .long("skip-locals-init")
+            .help(r#"Mark wrappers returning out(..) arrays with [SkipLocalsInit] from C# 9, so their stack
+    buffer isn't zeroed first. Only sound if every such function writes the whole array."#))
+        .arg(Arg::with_name("utf8-string-buffers")
+            .long("utf8-string-buffers")
+            .help(r#"Have wrappers encode UTF-8 string arguments themselves, on the stack when short and into
+    a pooled array when long, rather than via the allocating runtime marshaller. From C# 8."#))
+        .arg(Arg::with_name("generated-code-attribute")
+            .long("generated-code-attribute")
+            .help("Mark each generated type with a [GeneratedCode] attribute crediting this tool"))
+        .arg(Arg::with_name("generated-code-tool")
+            .long("generated-code-tool")
+            .value_name("Name")
+            .requires("generated-code-attribute")
+            .help("The tool name to credit in [GeneratedCode] attributes, instead of dotnet-bindgen-cli")
+            .takes_value(true))
+        .arg(Arg::with_name("generated-code-version")
+            .long("generated-code-version")
+            .value_name("Version")
+            .requires("generated-code-attribute")
+            .help("The tool version to credit in [GeneratedCode] attributes, instead of this tool's own")
+            .takes_value(true))
         .get_matches();
 
     let source_binaries = vec![
@@ -175,7 +386,53 @@ fn main() -> Result<(), &'static str> {
 
     let source_output_dir = Path::new(matches.value_of("source-output-dir").unwrap());
 
-    generate_bindings(source_binaries, &source_output_dir)?;
+    let codegen_config = codegen::CodegenConfig {
+        optional_ref_overloads: matches.is_present("optional-ref-overloads"),
+        widen_int_args: matches.is_present("widen-int-args"),
+        widen_unsigned_returns: matches.is_present("widen-unsigned-returns"),
+        narrowing: matches.value_of("narrowing").unwrap().parse()?,
+        length_overloads: matches.is_present("length-overloads"),
+        struct_equality: matches.is_present("struct-equality"),
+        header: matches.value_of("header").unwrap().parse()?,
+        try_pattern: matches.is_present("try-pattern"),
+        async_dispose: matches.is_present("async-dispose"),
+        module_namespaces: matches.is_present("module-namespaces"),
+        namespace_root: matches.value_of("namespace-root").map(|root| root.to_owned()),
+        charset: matches.value_of("charset").unwrap().parse()?,
+        must_use_attribute: matches.is_present("must-use-attribute"),
+        regions: matches.is_present("regions"),
+        partial_members: matches.is_present("emit-partial-members"),
+        lang_version: matches.value_of("lang-version").unwrap().parse()?,
+        ref_readonly: matches.is_present("ref-readonly"),
+        skip_locals_init: matches.is_present("skip-locals-init"),
+        utf8_string_buffers: matches.is_present("utf8-string-buffers"),
+        visibility: codegen::MethodVisibility {
+            externs: matches.value_of("extern-visibility").unwrap().parse()?,
+            wrappers: matches.value_of("wrapper-visibility").unwrap().parse()?,
+        },
+        max_width: matches
+            .value_of("max-width")
+            .map(|width| width.parse().map_err(|_| "The max width must be a number of columns"))
+            .transpose()?,
+        generated_code: match matches.is_present("generated-code-attribute") {
+            true => {
+                let default = codegen::GeneratedCodeTool::default();
+                Some(codegen::GeneratedCodeTool::new(
+                    matches.value_of("generated-code-tool").unwrap_or(default.name()),
+                    matches.value_of("generated-code-version").unwrap_or(default.version()),
+                )?)
+            }
+            false => None,
+        },
+    };
+
+    generate_bindings(
+        source_binaries,
+        source_output_dir,
+        &codegen_config,
+        matches.is_present("emit-bench"),
+        matches.is_present("emit-roundtrip-test"),
+    )?;
 
     Ok(())
 }
@@ -11,7 +11,7 @@ pub enum NativePlatform {
 
 impl NativePlatform {
     /// The string representing this RID that dotnet understands
-    pub fn to_dotnet_rid_string(&self) -> &'static str {
+    pub fn to_dotnet_rid_string(self) -> &'static str {
         match self {
             NativePlatform::WinX64 => "win-x64",
             NativePlatform::LinuxX64 => "linux-x64",
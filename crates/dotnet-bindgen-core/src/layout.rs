@@ -0,0 +1,244 @@
+//! C ABI layout calculations for described types.
+
+use crate::{BindgenStructDescriptor, BindgenTypeDescriptor};
+
+/// The properties of a target that the C ABI layout of a type depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetAbi {
+    /// The width of a pointer in bytes
+    pub pointer_width: usize,
+
+    /// The largest alignment any scalar is given, in bytes. Scalars are otherwise aligned to their
+    /// size, but eg the System V ABI for 32 bit x86 only aligns u64 and f64 to 4 bytes.
+    pub max_scalar_align: usize,
+}
+
+impl TargetAbi {
+    pub const X86_64: Self = Self { pointer_width: 8, max_scalar_align: 8 };
+
+    /// 32 bit x86 on Linux and macOS, which aligns u64 and f64 struct fields to 4 bytes.
+    pub const X86_SYSV: Self = Self { pointer_width: 4, max_scalar_align: 4 };
+
+    /// 32 bit x86 on Windows, which aligns u64 and f64 to 8 bytes like every other target.
+    pub const X86_WINDOWS: Self = Self { pointer_width: 4, max_scalar_align: 8 };
+}
+
+/// The size and alignment of a type in bytes, as it would be laid out by a C compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeLayout {
+    pub size: usize,
+    pub align: usize,
+}
+
+impl TypeLayout {
+    fn scalar(size: usize, target: TargetAbi) -> Self {
+        Self { size, align: size.min(target.max_scalar_align) }
+    }
+
+    /// Lays out the given fields in order, as a `#[repr(C)]` struct containing them would be.
+    ///
+    /// Each field is placed at the next offset that satisfies its alignment, and the total size is
+    /// rounded up to a multiple of the largest field alignment so that arrays of the struct keep
+    /// every element aligned.
+    pub fn sequential(fields: impl IntoIterator<Item = TypeLayout>) -> Self {
+        let mut size = 0;
+        let mut align = 1;
+
+        for field in fields {
+            size = round_up(size, field.align) + field.size;
+            align = align.max(field.align);
+        }
+
+        Self {
+            size: round_up(size, align),
+            align,
+        }
+    }
+}
+
+fn round_up(offset: usize, align: usize) -> usize {
+    offset.next_multiple_of(align)
+}
+
+impl BindgenTypeDescriptor {
+    /// The C ABI layout of this type on the given target.
+    ///
+    /// Types with no layout of their own (`Void`, and opaque types) give `None`, as does any
+    /// struct containing one.
+    pub fn layout(&self, target: TargetAbi) -> Option<TypeLayout> {
+        match self {
+            BindgenTypeDescriptor::Void => None,
+            BindgenTypeDescriptor::Int { width, .. } => Some(TypeLayout::scalar(*width as usize / 8, target)),
+            BindgenTypeDescriptor::Size { .. } => Some(TypeLayout::scalar(target.pointer_width, target)),
+            BindgenTypeDescriptor::Float { width } => Some(TypeLayout::scalar(*width as usize / 8, target)),
+            BindgenTypeDescriptor::Bool => Some(TypeLayout::scalar(1, target)),
+
+            // Crosses the boundary as a SliceAbi, ie a pointer followed by a u64 length
+            BindgenTypeDescriptor::Slice { .. } => Some(TypeLayout::sequential(vec![
+                TypeLayout::scalar(target.pointer_width, target),
+                TypeLayout::scalar(8, target),
+            ])),
+            BindgenTypeDescriptor::Ref { .. }
+            | BindgenTypeDescriptor::NullableRef { .. }
+            | BindgenTypeDescriptor::Str { .. }
+            | BindgenTypeDescriptor::Ptr { .. } => {
+                Some(TypeLayout::scalar(target.pointer_width, target))
+            }
+            BindgenTypeDescriptor::Array { elem_type, len } => {
+                elem_type.layout(target).map(|elem| TypeLayout {
+                    size: elem.size * len,
+                    align: elem.align,
+                })
+            }
+            BindgenTypeDescriptor::Struct(s) => s.layout(target),
+            BindgenTypeDescriptor::Opaque(_) => None,
+
+            // Only the layout of C-like enums is well defined, as they are just their repr type
+            BindgenTypeDescriptor::Enum(e) if e.c_like => e.repr.layout(target),
+            BindgenTypeDescriptor::Enum(_) => None,
+        }
+    }
+}
+
+impl BindgenStructDescriptor {
+    /// The C ABI layout of this struct on the given target.
+    pub fn layout(&self, target: TargetAbi) -> Option<TypeLayout> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|f| f.ty.layout(target))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(TypeLayout::sequential(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BindgenStructFieldDescriptor, BindgenTypeDescribe};
+    use std::mem::{align_of, size_of};
+
+    const HOST: TargetAbi = TargetAbi {
+        pointer_width: size_of::<usize>(),
+        max_scalar_align: align_of::<u64>(),
+    };
+
+    fn struct_of(fields: Vec<BindgenTypeDescriptor>) -> BindgenTypeDescriptor {
+        BindgenTypeDescriptor::Struct(BindgenStructDescriptor {
+            name: "Test".into(),
+            fields: fields
+                .into_iter()
+                .enumerate()
+                .map(|(idx, ty)| BindgenStructFieldDescriptor {
+                    name: format!("field_{}", idx),
+                    ty,
+                    string_buffer: false,
+                })
+                .collect(),
+            cfg: None,
+            native_marshalling: false,
+            as_type: None,
+            namespace: None,
+            module_path: "test".into(),
+        })
+    }
+
+    fn describe<T: BindgenTypeDescribe>() -> BindgenTypeDescriptor {
+        T::describe()
+    }
+
+    fn host_layout<T>() -> Option<TypeLayout> {
+        Some(TypeLayout { size: size_of::<T>(), align: align_of::<T>() })
+    }
+
+    #[repr(C)]
+    struct Mixed {
+        _a: u8,
+        _b: u32,
+        _c: u16,
+    }
+
+    #[repr(C)]
+    struct TrailingPadding {
+        _a: u64,
+        _b: u8,
+    }
+
+    #[repr(C)]
+    struct Nested {
+        _a: u8,
+        _inner: TrailingPadding,
+        _c: [u16; 3],
+    }
+
+    #[repr(C)]
+    struct Floats {
+        _flag: bool,
+        _x: f64,
+        _y: f32,
+    }
+
+    #[repr(C)]
+    struct SliceAbi {
+        _ptr: *const u8,
+        _len: u64,
+    }
+
+    fn trailing_padding() -> BindgenTypeDescriptor {
+        struct_of(vec![describe::<u64>(), describe::<u8>()])
+    }
+
+    #[test]
+    fn mixed_width_fields() {
+        let ty = struct_of(vec![describe::<u8>(), describe::<u32>(), describe::<u16>()]);
+        assert_eq!(ty.layout(HOST), host_layout::<Mixed>());
+    }
+
+    #[test]
+    fn trailing_padding_is_included() {
+        assert_eq!(trailing_padding().layout(HOST), host_layout::<TrailingPadding>());
+    }
+
+    #[test]
+    fn nested_structs_and_arrays() {
+        let ty = struct_of(vec![describe::<u8>(), trailing_padding(), describe::<[u16; 3]>()]);
+        assert_eq!(ty.layout(HOST), host_layout::<Nested>());
+    }
+
+    #[test]
+    fn bools_and_floats() {
+        let ty = struct_of(vec![describe::<bool>(), describe::<f64>(), describe::<f32>()]);
+        assert_eq!(ty.layout(HOST), host_layout::<Floats>());
+    }
+
+    #[test]
+    fn slices_are_a_pointer_and_length() {
+        assert_eq!(describe::<&[u8]>().layout(HOST), host_layout::<SliceAbi>());
+    }
+
+    #[test]
+    fn wide_scalars_are_less_aligned_on_x86_sysv() {
+        assert_eq!(
+            trailing_padding().layout(TargetAbi::X86_SYSV),
+            Some(TypeLayout { size: 12, align: 4 })
+        );
+        assert_eq!(
+            describe::<&[u8]>().layout(TargetAbi::X86_SYSV),
+            Some(TypeLayout { size: 12, align: 4 })
+        );
+    }
+
+    #[test]
+    fn x86_windows_aligns_wide_scalars_to_their_size() {
+        let ty = struct_of(vec![describe::<u8>(), describe::<u64>()]);
+        assert_eq!(ty.layout(TargetAbi::X86_SYSV), Some(TypeLayout { size: 12, align: 4 }));
+        assert_eq!(ty.layout(TargetAbi::X86_WINDOWS), Some(TypeLayout { size: 16, align: 8 }));
+    }
+
+    #[test]
+    fn types_without_a_layout() {
+        assert_eq!(BindgenTypeDescriptor::Void.layout(HOST), None);
+        assert_eq!(struct_of(vec![describe::<u8>(), BindgenTypeDescriptor::Void]).layout(HOST), None);
+    }
+}
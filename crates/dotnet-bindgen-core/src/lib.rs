@@ -2,6 +2,13 @@
 //!
 //! This component is intended to be fairly minimal, to reduce the impact of having it included in client code.
 
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+
+mod layout;
+pub use layout::{TargetAbi, TypeLayout};
+
 /// Marker trait for types that are trivially stable ABI types
 pub trait FfiStable {}
 
@@ -9,13 +16,31 @@ macro_rules! trivially_ffi_stable {
     ($($ty:ident),*) => { $( impl FfiStable for $ty {})* }
 }
 
-trivially_ffi_stable!(i8, i16, i32, i64, u8, u16, u32, u64);
+trivially_ffi_stable!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+// All reference types to FfiStable types are also FfiStable
+impl<T: FfiStable> FfiStable for &T {}
+impl<T: FfiStable> FfiStable for &mut T {}
+
+// Thin pointers are FfiStable regardless of what they point to, which is what allows opaque types
+// to be passed around by pointer.
+impl<T> FfiStable for *const T {}
+impl<T> FfiStable for *mut T {}
+
+// Option<&T> is guaranteed to have the same representation as a (possibly null) pointer.
+impl<T: FfiStable> FfiStable for Option<&T> {}
 
-// All reference types and pointer types to FfiStable types are also FfiStable
-impl<'a, T: FfiStable> FfiStable for &'a T {}
-impl<'a, T: FfiStable> FfiStable for &'a mut T {}
-impl<T: FfiStable> FfiStable for *const T {}
-impl<T: FfiStable> FfiStable for *mut T {}
+// Fixed size arrays are laid out exactly like the equivalent C array.
+impl<T: FfiStable, const N: usize> FfiStable for [T; N] {}
+
+/// Marker trait for types that may be fields of an FfiStable struct
+///
+/// As well as every FfiStable type, this includes `bool` and C-like enums. Those are converted
+/// when passed as arguments, but already have a C compatible layout when inside a struct.
+pub trait FfiStableField {}
+
+impl<T: FfiStable> FfiStableField for T {}
+impl FfiStableField for bool {}
 
 /// Defines how to translate a non-trivial type to/from a stable ABI type
 pub trait BindgenAbiConvert {
@@ -43,9 +68,9 @@ impl<T: FfiStable> BindgenAbiConvert for T {
 
 /// Explicitly map booleans to uint8s to cross the ffi boundary.
 ///
-/// The C99 standard only says that the representation of a bool must be large enough to hold 0 or
-/// 1. In practice this almost always means they are represented by a uint8_t by C compilers. The Rust
-/// specification explicitly doens't say how it might be represented.
+/// The C99 standard only says that the representation of a bool must be large enough to hold 0
+/// or 1. In practice this almost always means they are represented by a uint8_t by C compilers.
+/// The Rust specification explicitly doens't say how it might be represented.
 impl BindgenAbiConvert for bool {
     type AbiType = u8;
 
@@ -64,6 +89,117 @@ impl BindgenTypeDescribe for bool {
     }
 }
 
+/// String arguments cross the boundary as a null terminated UTF-8 string.
+///
+/// Converting back to a null terminated string has to allocate, and the allocation is never freed.
+impl BindgenAbiConvert for &str {
+    type AbiType = *const c_char;
+
+    // Only ever called by the generated thunks, on pointers handed over by the dotnet marshaller
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn from_abi_type(abi_value: Self::AbiType) -> Self {
+        unsafe { CStr::from_ptr(abi_value) }
+            .to_str()
+            .expect("String arguments must be valid UTF-8")
+    }
+
+    fn to_abi_type(self) -> Self::AbiType {
+        CString::new(self)
+            .expect("Strings can't contain interior null bytes")
+            .into_raw()
+    }
+}
+
+impl BindgenTypeDescribe for &str {
+    fn describe() -> BindgenTypeDescriptor {
+        BindgenTypeDescriptor::Str { wide: false, utf8: true }
+    }
+}
+
+/// A null terminated string of narrow code units in no particular encoding.
+///
+/// Unlike `&str` arguments these needn't be valid UTF-8, so can be marshalled in the system's ANSI
+/// code page.
+impl BindgenAbiConvert for &CStr {
+    type AbiType = *const c_char;
+
+    // Only ever called by the generated thunks, on pointers handed over by the dotnet marshaller
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn from_abi_type(abi_value: Self::AbiType) -> Self {
+        unsafe { CStr::from_ptr(abi_value) }
+    }
+
+    fn to_abi_type(self) -> Self::AbiType {
+        self.as_ptr()
+    }
+}
+
+impl BindgenTypeDescribe for &CStr {
+    fn describe() -> BindgenTypeDescriptor {
+        BindgenTypeDescriptor::Str { wide: false, utf8: false }
+    }
+}
+
+/// A borrowed, null terminated UTF-16 string, as passed by dotnet for `UnmanagedType.LPWStr`.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct WideStr<'a> {
+    ptr: *const u16,
+    _lifetime: PhantomData<&'a u16>,
+}
+
+impl<'a> WideStr<'a> {
+    /// The UTF-16 code units of the string, not including the null terminator.
+    pub fn as_slice(&self) -> &'a [u16] {
+        let mut len = 0;
+        unsafe {
+            while *self.ptr.add(len) != 0 {
+                len += 1;
+            }
+            std::slice::from_raw_parts(self.ptr, len)
+        }
+    }
+
+    /// Decodes the string, replacing any invalid UTF-16 with the replacement character.
+    pub fn to_string_lossy(&self) -> String {
+        String::from_utf16_lossy(self.as_slice())
+    }
+}
+
+impl FfiStable for WideStr<'_> {}
+
+impl BindgenTypeDescribe for WideStr<'_> {
+    fn describe() -> BindgenTypeDescriptor {
+        BindgenTypeDescriptor::Str { wide: true, utf8: false }
+    }
+}
+
+/// A half precision float, which crosses the boundary as its IEEE 754 binary16 bits.
+///
+/// Rust has no stable `f16` type, so this stands in for ones such as `half::f16` at the boundary,
+/// eg `Half::from_bits(value.to_bits())`. It is bound as a C# `System.Half` where available.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Half(u16);
+
+impl Half {
+    pub const fn from_bits(bits: u16) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+}
+
+impl FfiStable for Half {}
+
+impl BindgenTypeDescribe for Half {
+    fn describe() -> BindgenTypeDescriptor {
+        BindgenTypeDescriptor::Float { width: 16 }
+    }
+}
+
 /// FfiStable representation of a slice type
 ///
 /// This representation is written to look very similar to the actual underlying
@@ -101,11 +237,49 @@ pub enum BindgenTypeDescriptor {
         width: u8,
         signed: bool,
     },
+    /// A `usize` or `isize`, which is as wide as a pointer on the target
+    Size {
+        signed: bool,
+    },
+    /// An IEEE 754 floating point number with the given width in bits
+    Float {
+        width: u8,
+    },
     Bool,
     Slice {
         elem_type: Box<BindgenTypeDescriptor>,
     },
+    /// A `&T` or `&mut T`, which crosses the boundary as a non-null pointer to a T
+    Ref {
+        target: Box<BindgenTypeDescriptor>,
+        mutable: bool,
+    },
+    /// An `Option<&T>`, which crosses the boundary as a possibly null pointer to a T
+    NullableRef {
+        target: Box<BindgenTypeDescriptor>,
+    },
     Struct(BindgenStructDescriptor),
+    /// A type whose layout isn't exposed, which can only be passed around behind a pointer
+    Opaque(BindgenOpaqueDescriptor),
+    Enum(BindgenEnumDescriptor),
+    /// A raw `*const T` or `*mut T`
+    Ptr {
+        inner: Box<BindgenTypeDescriptor>,
+        mutable: bool,
+    },
+    /// A borrowed string, which crosses the boundary as a null terminated pointer to either narrow
+    /// (`&str`, `&CStr`), or UTF-16 (`WideStr`) code units.
+    Str {
+        wide: bool,
+
+        /// Whether the code units must be valid UTF-8, as they must for `&str`.
+        utf8: bool,
+    },
+    /// A fixed size `[T; N]`
+    Array {
+        elem_type: Box<BindgenTypeDescriptor>,
+        len: usize,
+    },
 }
 
 #[repr(C)]
@@ -113,6 +287,13 @@ pub enum BindgenTypeDescriptor {
 pub struct BindgenFunctionArgumentDescriptor {
     pub name: String,
     pub ty: BindgenTypeDescriptor,
+
+    /// Whether this `&mut T` argument was listed in `#[dotnet_bindgen(out(..))]`, ie the function
+    /// only ever writes through it.
+    pub out: bool,
+
+    /// How this string argument is encoded, if overridden via `#[dotnet_bindgen(charset(..))]`
+    pub charset: Option<String>,
 }
 
 #[repr(C)]
@@ -126,6 +307,57 @@ pub struct BindgenFunctionDescriptor {
 
     pub arguments: Vec<BindgenFunctionArgumentDescriptor>,
     pub return_ty: BindgenTypeDescriptor,
+
+    /// The cfg predicate gating this function, if one was given via `#[dotnet_bindgen(cfg(..))]`
+    pub cfg: Option<String>,
+
+    /// The operating systems this function is supported on, as given via
+    /// `#[dotnet_bindgen(platforms(..))]`. Empty if the function is supported everywhere.
+    pub platforms: Vec<String>,
+
+    /// Set by `#[dotnet_bindgen(no_try)]`, excluding this function from being exposed with the
+    /// C# Try pattern.
+    pub no_try: bool,
+
+    /// C# source to use as the body of the generated wrapper, if one was given via
+    /// `#[dotnet_bindgen(body = "..")]`.
+    pub body: Option<String>,
+
+    /// Set by `#[dotnet_bindgen(release)]`, marking this function as freeing the handle it takes.
+    pub release: bool,
+
+    /// Set by `#[dotnet_bindgen(bench)]`, selecting this function for the benchmark harness.
+    pub bench: bool,
+
+    /// Set by `#[dotnet_bindgen(roundtrip)]`, marking this function as returning its single
+    /// argument unchanged, to be called by the generated round trip test.
+    pub roundtrip: bool,
+
+    /// Set if the function is `#[must_use]`, to the reason given with it (which may be empty).
+    pub must_use: Option<String>,
+
+    /// The function is `-> !`, so never returns to its caller. Its `return_ty` is then `Void`.
+    pub diverges: bool,
+
+    /// The bound enum that the function's `i32` return value should be converted to, if one was
+    /// given via `#[dotnet_bindgen(error_enum = "..")]`.
+    pub error_enum: Option<String>,
+
+    /// The native library the function is imported from, if it was declared in an
+    /// `#[dotnet_bindgen(library = "..")]` extern block rather than defined by this crate. Its
+    /// `thunk_name` is then the symbol it is linked against.
+    pub library: Option<String>,
+
+    /// The C# namespace to generate this function into, if one was given via
+    /// `#[dotnet_bindgen(namespace = "..")]`.
+    pub namespace: Option<String>,
+
+    /// The static class to generate this function into, if one was given via
+    /// `#[dotnet_bindgen(class = "..")]`.
+    pub class: Option<String>,
+
+    /// The path of the module the function was defined in, eg `my_lib::geometry`
+    pub module_path: String,
 }
 
 #[repr(C)]
@@ -136,6 +368,10 @@ pub struct BindgenStructFieldDescriptor {
 
     /// The type of the field being described
     pub ty: BindgenTypeDescriptor,
+
+    /// Set by `#[dotnet_bindgen(string_buffers(..))]`, marking this `[u8; N]` or `[u16; N]` field
+    /// as holding a nul terminated string.
+    pub string_buffer: bool,
 }
 
 #[repr(C)]
@@ -145,15 +381,100 @@ pub struct BindgenStructDescriptor {
     pub name: String,
 
     /// An ordered set of the fields that appear in this struct.
-    pub fields: Vec<BindgenStructFieldDescriptor>
+    pub fields: Vec<BindgenStructFieldDescriptor>,
+
+    /// The cfg predicate gating this struct, if one was given via `#[dotnet_bindgen(cfg(..))]`
+    pub cfg: Option<String>,
+
+    /// Set if the struct was marked with `#[dotnet_bindgen(native_marshalling)]`, asking for a
+    /// custom marshaller stub to be generated for it.
+    pub native_marshalling: bool,
+
+    /// The idiomatic C# type the struct should get conversions to and from, if one was given via
+    /// `#[dotnet_bindgen(as = "..")]`.
+    pub as_type: Option<String>,
+
+    /// The C# namespace to generate this struct into, if one was given via
+    /// `#[dotnet_bindgen(namespace = "..")]`.
+    pub namespace: Option<String>,
+
+    /// The path of the module the item was defined in, eg `my_lib::geometry`
+    pub module_path: String,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindgenOpaqueDescriptor {
+    /// The original name of the struct that received the #[dotnet_bindgen(opaque)] attribute
+    pub name: String,
+
+    /// The cfg predicate gating this type, if one was given via `#[dotnet_bindgen(cfg(..))]`
+    pub cfg: Option<String>,
+    /// The C# namespace to generate this type into, if one was given via
+    /// `#[dotnet_bindgen(namespace = "..")]`.
+    pub namespace: Option<String>,
+    /// The path of the module the item was defined in, eg `my_lib::geometry`
+    pub module_path: String,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindgenEnumVariantDescriptor {
+    /// The name as it appears in the original enum definition
+    pub name: String,
+
+    /// The discriminant of this variant, exactly as rustc assigned it.
+    pub discriminant: i128,
+}
 
 #[repr(C)]
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindgenEnumDescriptor {
+    /// The original name of the enum that received the #[dotnet_bindgen] attribute
+    pub name: String,
+
+    /// The integer type given in the enum's `#[repr(..)]` attribute
+    pub repr: Box<BindgenTypeDescriptor>,
+
+    /// An ordered set of the variants that appear in this enum.
+    pub variants: Vec<BindgenEnumVariantDescriptor>,
+
+    /// True if no variant carries any data, in which case values of the enum can cross the
+    /// boundary as its repr type.
+    pub c_like: bool,
+
+    /// The cfg predicate gating this enum, if one was given via `#[dotnet_bindgen(cfg(..))]`
+    pub cfg: Option<String>,
+    /// The C# namespace to generate this enum into, if one was given via
+    /// `#[dotnet_bindgen(namespace = "..")]`.
+    pub namespace: Option<String>,
+    /// The path of the module the item was defined in, eg `my_lib::geometry`
+    pub module_path: String,
+}
+
+/// Identifies the crate that some set of exports were defined in
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindgenCrateDescriptor {
+    /// The `CARGO_PKG_NAME` of the crate
+    pub name: String,
+
+    /// The `CARGO_PKG_VERSION` of the crate
+    pub version: String,
+}
+
+
+#[repr(C)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub enum BindgenExportDescriptor {
     Function(BindgenFunctionDescriptor),
     Struct(BindgenStructDescriptor),
+    Opaque(BindgenOpaqueDescriptor),
+    Enum(BindgenEnumDescriptor),
+
+    /// Emitted alongside every other export, so may appear many times for the same crate.
+    Crate(BindgenCrateDescriptor),
 }
 
 
@@ -194,14 +515,66 @@ simple_describe![
     u16 => Int { width: 16, signed: false },
     u32 => Int { width: 32, signed: false },
     u64 => Int { width: 64, signed: false },
+    isize => Size { signed: true  },
+    usize => Size { signed: false },
+    f32 => Float { width: 32 },
+    f64 => Float { width: 64 },
 ];
 
-impl<'a, T: FfiStable + BindgenTypeDescribe> BindgenTypeDescribe for &'a [T] {
+impl<T: FfiStable + BindgenTypeDescribe> BindgenTypeDescribe for &[T] {
     fn describe() -> BindgenTypeDescriptor {
         let elem_type = Box::new(<T as BindgenTypeDescribe>::describe());
         BindgenTypeDescriptor::Slice { elem_type }
     }
 }
 
+impl<T: FfiStable + BindgenTypeDescribe> BindgenTypeDescribe for &T {
+    fn describe() -> BindgenTypeDescriptor {
+        let target = Box::new(<T as BindgenTypeDescribe>::describe());
+        BindgenTypeDescriptor::Ref { target, mutable: false }
+    }
+}
+
+impl<T: FfiStable + BindgenTypeDescribe> BindgenTypeDescribe for &mut T {
+    fn describe() -> BindgenTypeDescriptor {
+        let target = Box::new(<T as BindgenTypeDescribe>::describe());
+        BindgenTypeDescriptor::Ref { target, mutable: true }
+    }
+}
+
+impl<T: FfiStable + BindgenTypeDescribe> BindgenTypeDescribe for Option<&T> {
+    fn describe() -> BindgenTypeDescriptor {
+        let target = Box::new(<T as BindgenTypeDescribe>::describe());
+        BindgenTypeDescriptor::NullableRef { target }
+    }
+}
+
+impl<T: FfiStable + BindgenTypeDescribe, const N: usize> BindgenTypeDescribe for [T; N] {
+    fn describe() -> BindgenTypeDescriptor {
+        let elem_type = Box::new(<T as BindgenTypeDescribe>::describe());
+        BindgenTypeDescriptor::Array { elem_type, len: N }
+    }
+}
+
+impl<T: BindgenTypeDescribe> BindgenTypeDescribe for *const T {
+    fn describe() -> BindgenTypeDescriptor {
+        let inner = Box::new(<T as BindgenTypeDescribe>::describe());
+        BindgenTypeDescriptor::Ptr { inner, mutable: false }
+    }
+}
+
+impl<T: BindgenTypeDescribe> BindgenTypeDescribe for *mut T {
+    fn describe() -> BindgenTypeDescriptor {
+        let inner = Box::new(<T as BindgenTypeDescribe>::describe());
+        BindgenTypeDescriptor::Ptr { inner, mutable: true }
+    }
+}
+
+/// The names accepted for the whole seconds field of a struct converted to a TimeSpan.
+pub const TIMESPAN_SECONDS_FIELDS: &[&str] = &["secs", "seconds"];
+
+/// The names accepted for the nanoseconds field of a struct converted to a TimeSpan.
+pub const TIMESPAN_NANOS_FIELDS: &[&str] = &["nanos", "nanoseconds"];
+
 /// The generator discovers descriptors by scanning the binary for symbols that start with this prefix.
-pub const BINDGEN_DESCRIBE_PREFIX: &'static str = "__bindgen_describe";
+pub const BINDGEN_DESCRIBE_PREFIX: &str = "__bindgen_describe";
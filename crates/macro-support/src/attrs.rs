@@ -0,0 +1,265 @@
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+
+use crate::{bail_span, err_span, Diagnostic};
+
+/// The options given to the attribute itself, eg `#[dotnet_bindgen(cfg(feature = "foo"))]`
+#[derive(Debug, Default)]
+pub struct BindgenAttrs {
+    /// A cfg predicate gating both the annotated item and all of its generated binding metadata.
+    ///
+    /// rustc evaluates (and strips) any plain `#[cfg]` attributes before this macro is invoked, so
+    /// the only way for the generated bindings to know about a gating condition is for it to be
+    /// given here instead.
+    pub cfg: Option<TokenStream>,
+
+    /// The annotated struct should be exposed without its layout, to be passed around by pointer.
+    pub opaque: bool,
+
+    /// The operating systems the annotated function is supported on, empty if unrestricted.
+    pub platforms: Vec<String>,
+
+    /// The `&mut T` arguments of the annotated function which are only ever written through.
+    pub out: Vec<syn::Ident>,
+
+    /// The annotated function shouldn't be exposed with the C# Try pattern, even if it looks like it.
+    pub no_try: bool,
+
+    /// C# source to use as the body of the generated wrapper, in place of the usual forwarding
+    /// call. The body can still call the extern method, which is named `__bindgen_thunk_{name}`.
+    pub body: Option<String>,
+
+    /// The annotated function frees the single handle it takes, so can be used to dispose of it.
+    pub release: bool,
+
+    /// The annotated function should get a stub in the generated benchmark harness.
+    pub bench: bool,
+
+    /// The annotated function returns its single argument unchanged, so can be used to check that
+    /// values survive a round trip across the boundary.
+    pub roundtrip: bool,
+
+    /// How each of the named string arguments of the annotated function is encoded, overriding the
+    /// generator's default.
+    pub charsets: Vec<(syn::Ident, String)>,
+
+    /// The annotated struct should get a `[NativeMarshalling]` attribute naming a generated stub
+    /// marshaller, for customising how it crosses the boundary under source generated interop.
+    pub native_marshalling: bool,
+
+    /// The idiomatic C# type the annotated struct should get conversions to and from, eg
+    /// `#[dotnet_bindgen(as = "TimeSpan")]`.
+    pub as_type: Option<String>,
+
+    /// The name of a bound enum the wrapper should convert the annotated function's `i32` return
+    /// value to, eg `#[dotnet_bindgen(error_enum = "MyError")]`.
+    pub error_enum: Option<syn::Ident>,
+
+    /// The native library that the functions of the annotated extern block are imported from, eg
+    /// `#[dotnet_bindgen(library = "m")]`.
+    pub library: Option<String>,
+
+    /// The fixed size `[u8; N]` or `[u16; N]` fields of the annotated struct which hold a nul
+    /// terminated string, eg `#[dotnet_bindgen(string_buffers(label))]`.
+    pub string_buffers: Vec<syn::Ident>,
+
+    /// The C# namespace the annotated item should be generated into, in place of the one picked by
+    /// the generator, eg `#[dotnet_bindgen(namespace = "MyCompany.Native")]`.
+    pub namespace: Option<String>,
+
+    /// The name of the static class the annotated function should be generated into, in place of
+    /// `TopLevelMethods`, eg `#[dotnet_bindgen(class = "Bindings")]`.
+    pub class: Option<String>,
+
+    /// The name of each option that was given, and the span of its name, in the order given.
+    options: Vec<(String, Span)>,
+}
+
+/// The platforms that may be given to `#[dotnet_bindgen(platforms(..))]`, named as in dotnet
+const KNOWN_PLATFORMS: &[&str] = &["windows", "linux", "osx"];
+
+/// The string encodings that may be given to `#[dotnet_bindgen(charset(..))]`
+const KNOWN_CHARSETS: &[&str] = &["utf8", "ansi", "unicode"];
+
+/// Whether the string is usable as a (non-verbatim) C# identifier
+fn is_csharp_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The C# types that may be given to `#[dotnet_bindgen(as = "..")]`
+pub const KNOWN_AS_TYPES: &[&str] = &["TimeSpan"];
+
+impl BindgenAttrs {
+    pub fn parse(attrs: TokenStream) -> Result<Self, Diagnostic> {
+        let metas = Punctuated::<syn::NestedMeta, syn::Token![,]>::parse_terminated.parse2(attrs)?;
+
+        let mut parsed = Self::default();
+        for meta in metas.iter() {
+            let meta = match meta {
+                syn::NestedMeta::Meta(m) => m,
+                syn::NestedMeta::Lit(l) => bail_span!(l, "Expected a dotnet_bindgen option, not a literal"),
+            };
+
+            let key = meta.path().get_ident().map(|i| i.to_string());
+            if let Some(key) = &key {
+                parsed.options.push((key.clone(), meta.path().span()));
+            }
+
+            match (key.as_deref(), meta) {
+                (Some("cfg"), syn::Meta::List(list)) => {
+                    if list.nested.len() != 1 {
+                        bail_span!(list, "Expected a single cfg predicate, eg `cfg(feature = \"foo\")`");
+                    }
+                    if parsed.cfg.is_some() {
+                        bail_span!(list, "Duplicate cfg option, combine them with `cfg(all(..))`");
+                    }
+                    parsed.cfg = Some(list.nested.to_token_stream());
+                }
+                (Some("cfg"), _) => bail_span!(meta, "Expected a cfg predicate, eg `cfg(feature = \"foo\")`"),
+                (Some("opaque"), syn::Meta::Path(_)) => parsed.opaque = true,
+                (Some("no_try"), syn::Meta::Path(_)) => parsed.no_try = true,
+                (Some("release"), syn::Meta::Path(_)) => parsed.release = true,
+                (Some("bench"), syn::Meta::Path(_)) => parsed.bench = true,
+                (Some("roundtrip"), syn::Meta::Path(_)) => parsed.roundtrip = true,
+                (Some("native_marshalling"), syn::Meta::Path(_)) => parsed.native_marshalling = true,
+                (Some("body"), syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => {
+                    if parsed.body.is_some() {
+                        bail_span!(meta, "Duplicate body option");
+                    }
+                    parsed.body = Some(s.value());
+                }
+                (Some("as"), syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => {
+                    if parsed.as_type.is_some() {
+                        bail_span!(meta, "Duplicate as option");
+                    }
+                    if !KNOWN_AS_TYPES.contains(&s.value().as_str()) {
+                        bail_span!(s, "Expected one of the supported C# types, eg `as = \"TimeSpan\"`");
+                    }
+                    parsed.as_type = Some(s.value());
+                }
+                (Some("error_enum"), syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => {
+                    if parsed.error_enum.is_some() {
+                        bail_span!(meta, "Duplicate error_enum option");
+                    }
+                    parsed.error_enum = Some(s.parse().map_err(|_| err_span!(s, "Expected the name of a bound enum"))?);
+                }
+                (Some("library"), syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => {
+                    if parsed.library.is_some() {
+                        bail_span!(meta, "Duplicate library option");
+                    }
+                    if s.value().is_empty() {
+                        bail_span!(s, "Expected the name of a native library");
+                    }
+                    parsed.library = Some(s.value());
+                }
+                (Some("namespace"), syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => {
+                    if parsed.namespace.is_some() {
+                        bail_span!(meta, "Duplicate namespace option");
+                    }
+                    if !s.value().split('.').all(is_csharp_identifier) {
+                        bail_span!(s, "Expected a C# namespace, eg `namespace = \"MyCompany.Native\"`");
+                    }
+                    parsed.namespace = Some(s.value());
+                }
+                (Some("class"), syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => {
+                    if parsed.class.is_some() {
+                        bail_span!(meta, "Duplicate class option");
+                    }
+                    if !is_csharp_identifier(&s.value()) {
+                        bail_span!(s, "Expected the name of a C# class, eg `class = \"Bindings\"`");
+                    }
+                    parsed.class = Some(s.value());
+                }
+                (Some("namespace"), _) => bail_span!(meta, "Expected the namespace as a string, eg `namespace = \"MyCompany.Native\"`"),
+                (Some("class"), _) => bail_span!(meta, "Expected the class name as a string, eg `class = \"Bindings\"`"),
+                (Some("library"), _) => bail_span!(meta, "Expected the name of a library as a string, eg `library = \"m\"`"),
+                (Some("error_enum"), _) => bail_span!(meta, "Expected the name of an enum as a string, eg `error_enum = \"MyError\"`"),
+                (Some("as"), _) => bail_span!(meta, "Expected the C# type as a string, eg `as = \"TimeSpan\"`"),
+                (Some("body"), _) => bail_span!(meta, "Expected the C# method body as a string, eg `body = \"return 1;\"`"),
+                (Some("no_try"), _) => bail_span!(meta, "The no_try option doesn't take any arguments"),
+                (Some("release"), _) => bail_span!(meta, "The release option doesn't take any arguments"),
+                (Some("bench"), _) => bail_span!(meta, "The bench option doesn't take any arguments"),
+                (Some("roundtrip"), _) => bail_span!(meta, "The roundtrip option doesn't take any arguments"),
+                (Some("native_marshalling"), _) => bail_span!(meta, "The native_marshalling option doesn't take any arguments"),
+                (Some("opaque"), _) => bail_span!(meta, "The opaque option doesn't take any arguments"),
+                (Some("platforms"), syn::Meta::List(list)) => {
+                    if !parsed.platforms.is_empty() {
+                        bail_span!(list, "Duplicate platforms option");
+                    }
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Lit(syn::Lit::Str(s)) if KNOWN_PLATFORMS.contains(&s.value().as_str()) => {
+                                parsed.platforms.push(s.value());
+                            }
+                            _ => bail_span!(nested, "Expected one of \"windows\", \"linux\", or \"osx\""),
+                        }
+                    }
+                    if parsed.platforms.is_empty() {
+                        bail_span!(list, "Expected at least one platform, eg `platforms(\"windows\")`");
+                    }
+                }
+                (Some("out"), syn::Meta::List(list)) => {
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.get_ident().is_some() => {
+                                parsed.out.push(p.get_ident().unwrap().clone());
+                            }
+                            _ => bail_span!(nested, "Expected the name of an argument"),
+                        }
+                    }
+                }
+                (Some("string_buffers"), syn::Meta::List(list)) => {
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.get_ident().is_some() => {
+                                parsed.string_buffers.push(p.get_ident().unwrap().clone());
+                            }
+                            _ => bail_span!(nested, "Expected the name of a field"),
+                        }
+                    }
+                }
+                (Some("string_buffers"), _) => bail_span!(meta, "Expected a list of field names, eg `string_buffers(label)`"),
+                (Some("out"), _) => bail_span!(meta, "Expected a list of argument names, eg `out(result)`"),
+                (Some("charset"), syn::Meta::List(list)) => {
+                    for nested in list.nested.iter() {
+                        match nested {
+                            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                                path,
+                                lit: syn::Lit::Str(s),
+                                ..
+                            })) if path.get_ident().is_some() => {
+                                if !KNOWN_CHARSETS.contains(&s.value().as_str()) {
+                                    bail_span!(s, "Expected one of \"utf8\", \"ansi\", or \"unicode\"");
+                                }
+                                parsed.charsets.push((path.get_ident().unwrap().clone(), s.value()));
+                            }
+                            _ => bail_span!(nested, "Expected an argument name and its charset, eg `name = \"ansi\"`"),
+                        }
+                    }
+                }
+                (Some("charset"), _) => bail_span!(meta, "Expected a list of argument charsets, eg `charset(name = \"ansi\")`"),
+                (Some("platforms"), _) => bail_span!(meta, "Expected a list of platforms, eg `platforms(\"windows\", \"linux\")`"),
+                _ => bail_span!(meta, "Unrecognized dotnet_bindgen option"),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Checks that only the given options were passed to the attribute on some kind of item, eg
+    /// `check_allowed("enums", &["cfg", "namespace"])`, as any others would be silently ignored.
+    pub fn check_allowed(&self, kind: &str, allowed: &[&str]) -> Result<(), Diagnostic> {
+        match self.options.iter().find(|(option, _)| !allowed.contains(&option.as_str())) {
+            Some((option, span)) => Err(Diagnostic::span_error(
+                *span,
+                format!("The {} option can't be given to {}", option, kind),
+            )),
+            None => Ok(()),
+        }
+    }
+}
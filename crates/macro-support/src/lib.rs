@@ -1,24 +1,30 @@
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::fold::Fold;
 use syn::spanned::Spanned;
 
 mod error;
 pub use crate::error::Diagnostic;
 
+mod attrs;
+use crate::attrs::BindgenAttrs;
+
 use dotnet_bindgen_core::*;
 
 struct ExportedFunctionArg {
     name: proc_macro2::Ident,
     ty: syn::Type,
+    out: bool,
+    charset: Option<String>,
 }
 
 impl std::fmt::Debug for ExportedFunctionArg {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let ty_string = format!("syn::Type({})", self.ty.to_token_stream().to_string());
+        let ty_string = format!("syn::Type({})", self.ty.to_token_stream());
         write!(
             f,
-            "ExportedFunctionArg {{ name: {}, ty: {} }}",
-            self.name, ty_string
+            "ExportedFunctionArg {{ name: {}, ty: {}, out: {}, charset: {:?} }}",
+            self.name, ty_string, self.out, self.charset
         )
     }
 }
@@ -27,12 +33,39 @@ struct ExportedFunction {
     name: proc_macro2::Ident,
     arguments: Vec<ExportedFunctionArg>,
     return_ty: Option<syn::Type>,
+    cfg: Option<TokenStream>,
+    platforms: Vec<String>,
+    no_try: bool,
+    body: Option<String>,
+    release: bool,
+    bench: bool,
+    roundtrip: bool,
+    must_use: Option<String>,
+    diverges: bool,
+    error_enum: Option<syn::Ident>,
+
+    /// Where the function comes from, if it was declared in an `extern` block rather than defined
+    import: Option<ImportedSymbol>,
+
+    namespace: Option<String>,
+    class: Option<String>,
+}
+
+/// A function declared in an `extern` block, which is bound directly rather than through a thunk
+#[derive(Debug)]
+struct ImportedSymbol {
+    /// The native library the function is linked from, as given via
+    /// `#[dotnet_bindgen(library = "..")]`
+    library: String,
+
+    /// The symbol the function is linked against, from its `#[link_name]` if it has one
+    link_name: String,
 }
 
 impl std::fmt::Debug for ExportedFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let return_ty_string = match &self.return_ty {
-            Some(t) => format!("Some(syn::Type({}))", t.to_token_stream().to_string()),
+            Some(t) => format!("Some(syn::Type({}))", t.to_token_stream()),
             None => "None".to_string(),
         };
 
@@ -44,6 +77,12 @@ impl std::fmt::Debug for ExportedFunction {
     }
 }
 
+impl ExportedFunction {
+    fn thunk_name(&self) -> proc_macro2::Ident {
+        format_ident!("__bindgen_thunk_{}", self.name)
+    }
+}
+
 impl ToTokens for ExportedFunction {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let mut thunk_args = Vec::new();
@@ -65,10 +104,17 @@ impl ToTokens for ExportedFunction {
             });
 
             let name_string = name.to_string();
+            let out = arg.out;
+            let charset = match &arg.charset {
+                Some(charset) => quote! { Some(#charset.to_string()) },
+                None => quote! { None },
+            };
             arg_descriptors.push(quote! {
                 ::dotnet_bindgen::core::BindgenFunctionArgumentDescriptor {
                     name: #name_string.to_string(),
                     ty: <#ty as ::dotnet_bindgen::core::BindgenTypeDescribe>::describe(),
+                    out: #out,
+                    charset: #charset,
                 }
             })
         }
@@ -76,13 +122,66 @@ impl ToTokens for ExportedFunction {
         let arg_names = self.arguments.iter().map(|a| a.name.clone());
 
         let real_name = &self.name;
-        let thunk_name = format_ident!("__bindgen_thunk_{}", self.name);
+        let thunk_name = self.thunk_name();
         let descriptor_name = format_ident!("{}_func_{}", BINDGEN_DESCRIBE_PREFIX, self.name);
         let real_name_string = real_name.to_string();
-        let thunk_name_string = thunk_name.to_string();
+        let thunk_name_string = match &self.import {
+            Some(import) => import.link_name.clone(),
+            None => thunk_name.to_string(),
+        };
+        let cfg_attr = cfg_attr(&self.cfg);
+        let cfg_descriptor_frag = cfg_descriptor_frag(&self.cfg);
+        let platforms = &self.platforms;
+        let no_try = self.no_try;
+        let release = self.release;
+        let bench = self.bench;
+        let roundtrip = self.roundtrip;
+        let diverges = self.diverges;
+        let must_use = match &self.must_use {
+            Some(reason) => quote! { Some(#reason.to_string()) },
+            None => quote! { None },
+        };
+        let body = match &self.body {
+            Some(body) => quote! { Some(#body.to_string()) },
+            None => quote! { None },
+        };
+        let error_enum = match &self.error_enum {
+            Some(error_enum) => {
+                let error_enum = error_enum.to_string();
+                quote! { Some(#error_enum.to_string()) }
+            }
+            None => quote! { None },
+        };
+
+        let library = match &self.import {
+            Some(import) => {
+                let library = &import.library;
+                quote! { Some(#library.to_string()) }
+            }
+            None => quote! { None },
+        };
+        let namespace = optional_string_frag(&self.namespace);
+        let class = optional_string_frag(&self.class);
 
         let thunk = match &self.return_ty {
+            // An imported function is called directly, so there's no thunk to convert its arguments
+            // and everything it passes must already be FfiStable
+            _ if self.import.is_some() => {
+                let asserted_tys = self.arguments.iter()
+                    .map(|a| (a.name.to_string(), &a.ty))
+                    .chain(self.return_ty.iter().map(|ty| ("return".to_string(), ty)));
+                let assertions = asserted_tys.map(|(suffix, ty)| {
+                    let assert_struct_ident = format_ident!("_AssertFfiStable_{}_{}", self.name, suffix);
+                    quote_spanned! {ty.span()=>
+                        #cfg_attr
+                        #[allow(non_camel_case_types)]
+                        struct #assert_struct_ident where #ty: ::dotnet_bindgen::core::FfiStable {}
+                    }
+                });
+                quote! { #(#assertions)* }
+            }
             Some(ty) => quote!{
+                #cfg_attr
                 #[no_mangle]
                 pub extern "C" fn #thunk_name(
                     #(#thunk_args),*
@@ -92,7 +191,16 @@ impl ToTokens for ExportedFunction {
                     <#ty as ::dotnet_bindgen::core::BindgenAbiConvert>::to_abi_type(ret)
                 }
             },
+            None if self.diverges => quote! {
+                #cfg_attr
+                #[no_mangle]
+                pub extern "C" fn #thunk_name(#(#thunk_args),*) -> ! {
+                    #(#arg_conversions)*
+                    #real_name(#(#arg_names),*)
+                }
+            },
             None => quote! {
+                #cfg_attr
                 #[no_mangle]
                 pub extern "C" fn #thunk_name(#(#thunk_args),*) {
                     #(#arg_conversions)*
@@ -111,6 +219,7 @@ impl ToTokens for ExportedFunction {
         };
 
         let descriptor = quote! {
+            #cfg_attr
             #[no_mangle]
             pub fn #descriptor_name() -> ::dotnet_bindgen::core::BindgenExportDescriptor {
                 ::dotnet_bindgen::core::BindgenExportDescriptor::Function(
@@ -119,6 +228,20 @@ impl ToTokens for ExportedFunction {
                         thunk_name: #thunk_name_string.to_string(),
                         arguments: vec![#(#arg_descriptors),*],
                         return_ty: #return_ty_descriptor_frag,
+                        cfg: #cfg_descriptor_frag,
+                        platforms: vec![#(#platforms.to_string()),*],
+                        no_try: #no_try,
+                        body: #body,
+                        release: #release,
+                        bench: #bench,
+                        roundtrip: #roundtrip,
+                        must_use: #must_use,
+                        diverges: #diverges,
+                        error_enum: #error_enum,
+                        library: #library,
+                        namespace: #namespace,
+                        class: #class,
+                        module_path: module_path!().to_string(),
                     }
                 )
             }
@@ -135,11 +258,14 @@ struct ExportedStructField {
     name: proc_macro2::Ident,
     ty: syn::Type,
     span: proc_macro2::Span,
+
+    /// The field was listed in `#[dotnet_bindgen(string_buffers(..))]`
+    string_buffer: bool,
 }
 
 impl std::fmt::Debug for ExportedStructField {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let ty_string = format!("syn::Type({})", self.ty.to_token_stream().to_string());
+        let ty_string = format!("syn::Type({})", self.ty.to_token_stream());
         write!(f, "ExportedStructField {{ name: {}, ty: {} }}", self.name, ty_string)
     }
 }
@@ -148,6 +274,10 @@ struct ExportedStruct {
     name: proc_macro2::Ident,
     fields: Vec<ExportedStructField>,
     span: proc_macro2::Span,
+    cfg: Option<TokenStream>,
+    native_marshalling: bool,
+    as_type: Option<String>,
+    namespace: Option<String>,
 }
 
 impl std::fmt::Debug for ExportedStruct {
@@ -158,36 +288,41 @@ impl std::fmt::Debug for ExportedStruct {
 
 impl ExportedStruct {
     /// For each member, produces an item of the form
-    ///     `struct Assert3 where String: FfiStable`
+    ///     `struct Assert3 where String: FfiStableField`
     /// to fail compilation with an appropriate error message with an appropriate span when the
     /// exported struct can not be FfiStable
     fn ffi_stable_member_assertions(&self) -> TokenStream {
+        let cfg_attr = cfg_attr(&self.cfg);
         let mut assertions = Vec::new();
         for field in &self.fields {
             let assert_struct_ident = format_ident!("_AssertFfiStable_{}_{}", self.name, field.name);
             let ty = &field.ty;
             let ty_span = ty.span();
             assertions.push(quote_spanned!{ty_span=>
+                #cfg_attr
                 #[allow(non_camel_case_types)]
-                struct #assert_struct_ident where #ty: ::dotnet_bindgen::core::FfiStable {}
+                struct #assert_struct_ident where #ty: ::dotnet_bindgen::core::FfiStableField {}
             })
         }
 
         quote!{#(#assertions)*}
     }
 
-    /// Conditionally implements FfiStable for this struct, if all its underlying members are FfiStable.
+    /// Conditionally implements FfiStable for this struct, if all its underlying members can be
+    /// fields of an FfiStable struct.
     fn conditional_ffi_stable_impl(&self) -> TokenStream {
         let this_ty = &self.name;
+        let cfg_attr = cfg_attr(&self.cfg);
 
         let mut ffi_stable_impl = quote_spanned!{self.span=>
+            #cfg_attr
             impl ::dotnet_bindgen::core::FfiStable for #this_ty
             where
         };
         for field in &self.fields {
             let ty = &field.ty;
             ffi_stable_impl = quote_spanned!{field.span=>
-                #ffi_stable_impl #ty: ::dotnet_bindgen::core::FfiStable,
+                #ffi_stable_impl #ty: ::dotnet_bindgen::core::FfiStableField,
             }
         }
 
@@ -200,22 +335,33 @@ impl ExportedStruct {
     fn descriptor_impl(&self) -> TokenStream {
         let name = &self.name;
         let name_string = name.to_string();
+        let cfg_attr = cfg_attr(&self.cfg);
+        let cfg_descriptor_frag = cfg_descriptor_frag(&self.cfg);
+        let native_marshalling = self.native_marshalling;
+        let as_type = match &self.as_type {
+            Some(as_type) => quote! { Some(#as_type.to_string()) },
+            None => quote! { None },
+        };
+        let namespace = optional_string_frag(&self.namespace);
 
         let mut field_descriptors = Vec::new();
 
         for field in &self.fields {
             let field_name_string = field.name.to_string();
             let field_ty = &field.ty;
+            let string_buffer = field.string_buffer;
 
             field_descriptors.push(quote!{
                 ::dotnet_bindgen::core::BindgenStructFieldDescriptor {
                     name: #field_name_string.to_string(),
                     ty: <#field_ty as ::dotnet_bindgen::core::BindgenTypeDescribe>::describe(),
+                    string_buffer: #string_buffer,
                 }
             })
         }
 
         quote!{
+            #cfg_attr
             impl ::dotnet_bindgen::core::BindgenTypeDescribe for #name {
                 fn describe() -> ::dotnet_bindgen::core::BindgenTypeDescriptor {
                     ::dotnet_bindgen::core::BindgenTypeDescriptor::Struct(
@@ -223,7 +369,12 @@ impl ExportedStruct {
                             name: #name_string.to_string(),
                             fields: vec![
                                 #(#field_descriptors),*
-                            ]
+                            ],
+                            cfg: #cfg_descriptor_frag,
+                            native_marshalling: #native_marshalling,
+                            as_type: #as_type,
+                            namespace: #namespace,
+                            module_path: module_path!().to_string(),
                         }
                     )
                 }
@@ -235,8 +386,10 @@ impl ExportedStruct {
     fn descriptor_func(&self) -> TokenStream {
         let struct_name = &self.name;
         let descriptor_name = format_ident!("{}_struct_{}", BINDGEN_DESCRIBE_PREFIX, self.name);
+        let cfg_attr = cfg_attr(&self.cfg);
 
         quote!{
+            #cfg_attr
             #[no_mangle]
             #[allow(non_snake_case)]
             pub fn #descriptor_name() -> ::dotnet_bindgen::core::BindgenExportDescriptor {
@@ -268,10 +421,305 @@ impl ToTokens for ExportedStruct {
     }
 }
 
+/// A struct which is exposed only by name, and passed around by pointer.
+struct ExportedOpaque {
+    name: proc_macro2::Ident,
+    cfg: Option<TokenStream>,
+    namespace: Option<String>,
+}
+
+impl std::fmt::Debug for ExportedOpaque {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ExportedOpaque {{ name: {} }}", self.name)
+    }
+}
+
+impl ToTokens for ExportedOpaque {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let name = &self.name;
+        let name_string = name.to_string();
+        let descriptor_name = format_ident!("{}_opaque_{}", BINDGEN_DESCRIBE_PREFIX, self.name);
+        let cfg_attr = cfg_attr(&self.cfg);
+        let cfg_descriptor_frag = cfg_descriptor_frag(&self.cfg);
+        let namespace = optional_string_frag(&self.namespace);
+
+        let opaque_descriptor = quote! {
+            ::dotnet_bindgen::core::BindgenOpaqueDescriptor {
+                name: #name_string.to_string(),
+                cfg: #cfg_descriptor_frag,
+                namespace: #namespace,
+                module_path: module_path!().to_string(),
+            }
+        };
+
+        (quote! {
+            #cfg_attr
+            impl ::dotnet_bindgen::core::BindgenTypeDescribe for #name {
+                fn describe() -> ::dotnet_bindgen::core::BindgenTypeDescriptor {
+                    ::dotnet_bindgen::core::BindgenTypeDescriptor::Opaque(#opaque_descriptor)
+                }
+            }
+
+            #cfg_attr
+            #[no_mangle]
+            #[allow(non_snake_case)]
+            pub fn #descriptor_name() -> ::dotnet_bindgen::core::BindgenExportDescriptor {
+                ::dotnet_bindgen::core::BindgenExportDescriptor::Opaque(#opaque_descriptor)
+            }
+        }).to_tokens(tokens);
+    }
+}
+
+struct ExportedEnumVariant {
+    name: proc_macro2::Ident,
+    discriminant: Option<syn::Expr>,
+    has_fields: bool,
+}
+
+impl std::fmt::Debug for ExportedEnumVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let discriminant_string = self.discriminant.as_ref().map(|d| d.to_token_stream().to_string());
+        write!(
+            f,
+            "ExportedEnumVariant {{ name: {}, discriminant: {:?}, has_fields: {} }}",
+            self.name, discriminant_string, self.has_fields
+        )
+    }
+}
+
+struct ExportedEnum {
+    name: proc_macro2::Ident,
+    /// The integer type given in `#[repr(..)]`
+    repr: proc_macro2::Ident,
+    variants: Vec<ExportedEnumVariant>,
+    cfg: Option<TokenStream>,
+    namespace: Option<String>,
+}
+
+impl std::fmt::Debug for ExportedEnum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ExportedEnum {{ name: {}, repr: {}, variants: {:?} }}",
+            self.name, self.repr, self.variants
+        )
+    }
+}
+
+impl ExportedEnum {
+    fn is_c_like(&self) -> bool {
+        self.variants.iter().all(|v| !v.has_fields)
+    }
+
+    /// An expression evaluating to the discriminant of each variant as an i128.
+    ///
+    /// Variants of C-like enums can simply be cast to their discriminant. Variants that carry data
+    /// can't be, so instead the discriminants are recomputed following the same rules as rustc;
+    /// explicit discriminants are evaluated as constants of the repr type, and every other variant
+    /// is one more than the variant before it.
+    fn discriminant_exprs(&self) -> Vec<TokenStream> {
+        let name = &self.name;
+        let repr = &self.repr;
+
+        if self.is_c_like() {
+            return self.variants
+                .iter()
+                .map(|v| {
+                    let variant = &v.name;
+                    quote! { #name::#variant as #repr as i128 }
+                })
+                .collect();
+        }
+
+        let mut exprs = Vec::new();
+        let mut previous: Option<TokenStream> = None;
+        for variant in &self.variants {
+            let expr = match (&variant.discriminant, &previous) {
+                (Some(d), _) => quote! { { const D: #repr = #d; D as i128 } },
+                (None, Some(p)) => quote! { (#p) + 1 },
+                (None, None) => quote! { 0i128 },
+            };
+            exprs.push(expr.clone());
+            previous = Some(expr);
+        }
+
+        exprs
+    }
+
+    /// C-like enums cross the boundary as their repr type, checking for invalid values on the way in
+    ///
+    /// An invalid value panics in the thunk, which can't unwind out of an `extern "C"` function so
+    /// aborts the whole process. The generated C# enums document this on their declaration.
+    fn abi_convert_impl(&self) -> TokenStream {
+        if !self.is_c_like() {
+            return TokenStream::new();
+        }
+
+        let name = &self.name;
+        let name_string = name.to_string();
+        let repr = &self.repr;
+        let cfg_attr = cfg_attr(&self.cfg);
+        let variants = self.variants.iter().map(|v| &v.name);
+
+        quote! {
+            #cfg_attr
+            impl ::dotnet_bindgen::core::BindgenAbiConvert for #name {
+                type AbiType = #repr;
+
+                fn from_abi_type(abi_value: Self::AbiType) -> Self {
+                    #(
+                        if abi_value == #name::#variants as #repr {
+                            return #name::#variants;
+                        }
+                    )*
+                    panic!("{} is not a valid discriminant for {}", abi_value, #name_string)
+                }
+
+                fn to_abi_type(self) -> Self::AbiType {
+                    self as #repr
+                }
+            }
+
+            // As a struct field the enum is laid out exactly like its repr type, although unlike
+            // with arguments its value isn't checked on the way in.
+            #cfg_attr
+            impl ::dotnet_bindgen::core::FfiStableField for #name {}
+        }
+    }
+
+    /// A block that implements BindgenTypeDescribe for this enum
+    fn descriptor_impl(&self) -> TokenStream {
+        let name = &self.name;
+        let name_string = name.to_string();
+        let repr = &self.repr;
+        let c_like = self.is_c_like();
+        let cfg_attr = cfg_attr(&self.cfg);
+        let cfg_descriptor_frag = cfg_descriptor_frag(&self.cfg);
+        let namespace = optional_string_frag(&self.namespace);
+
+        let variant_descriptors = self.variants
+            .iter()
+            .zip(self.discriminant_exprs())
+            .map(|(variant, discriminant)| {
+                let variant_name_string = variant.name.to_string();
+                quote! {
+                    ::dotnet_bindgen::core::BindgenEnumVariantDescriptor {
+                        name: #variant_name_string.to_string(),
+                        discriminant: #discriminant,
+                    }
+                }
+            });
+
+        quote! {
+            #cfg_attr
+            impl ::dotnet_bindgen::core::BindgenTypeDescribe for #name {
+                fn describe() -> ::dotnet_bindgen::core::BindgenTypeDescriptor {
+                    ::dotnet_bindgen::core::BindgenTypeDescriptor::Enum(
+                        ::dotnet_bindgen::core::BindgenEnumDescriptor {
+                            name: #name_string.to_string(),
+                            repr: Box::new(<#repr as ::dotnet_bindgen::core::BindgenTypeDescribe>::describe()),
+                            variants: vec![
+                                #(#variant_descriptors),*
+                            ],
+                            c_like: #c_like,
+                            cfg: #cfg_descriptor_frag,
+                            namespace: #namespace,
+                            module_path: module_path!().to_string(),
+                        }
+                    )
+                }
+            }
+        }
+    }
+
+    /// A #[no_mangle]'d function which returns a BindgenExportDescriptor::Enum
+    fn descriptor_func(&self) -> TokenStream {
+        let enum_name = &self.name;
+        let descriptor_name = format_ident!("{}_enum_{}", BINDGEN_DESCRIBE_PREFIX, self.name);
+        let cfg_attr = cfg_attr(&self.cfg);
+
+        quote!{
+            #cfg_attr
+            #[no_mangle]
+            #[allow(non_snake_case)]
+            pub fn #descriptor_name() -> ::dotnet_bindgen::core::BindgenExportDescriptor {
+                let type_desc = <#enum_name as ::dotnet_bindgen::core::BindgenTypeDescribe>::describe();
+                ::dotnet_bindgen::core::BindgenExportDescriptor::Enum(
+                    match type_desc {
+                        ::dotnet_bindgen::core::BindgenTypeDescriptor::Enum(e) => e,
+                        _ => unreachable!(),
+                    }
+                )
+            }
+        }
+    }
+}
+
+impl ToTokens for ExportedEnum {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let abi_convert_impl = self.abi_convert_impl();
+        let descriptor_impl = self.descriptor_impl();
+        let descriptor_func = self.descriptor_func();
+
+        (quote! {
+            #abi_convert_impl
+            #descriptor_impl
+            #descriptor_func
+        }).to_tokens(tokens);
+    }
+}
+
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 enum Export {
     Func(ExportedFunction),
     Struct(ExportedStruct),
+    Opaque(ExportedOpaque),
+    Enum(ExportedEnum),
+}
+
+impl Export {
+    /// Distinguishes the generated items of this export from those of every other export
+    fn unique_suffix(&self) -> String {
+        match self {
+            Export::Func(f) => format!("func_{}", f.name),
+            Export::Struct(s) => format!("struct_{}", s.name),
+            Export::Opaque(o) => format!("opaque_{}", o.name),
+            Export::Enum(e) => format!("enum_{}", e.name),
+        }
+    }
+
+    fn cfg(&self) -> &Option<TokenStream> {
+        match self {
+            Export::Func(f) => &f.cfg,
+            Export::Struct(s) => &s.cfg,
+            Export::Opaque(o) => &o.cfg,
+            Export::Enum(e) => &e.cfg,
+        }
+    }
+
+    /// A #[no_mangle]'d function which returns a BindgenExportDescriptor::Crate, naming the crate
+    /// this export was defined in.
+    ///
+    /// There's no single place to put one of these per crate, so one is generated per export.
+    fn crate_descriptor_func(&self) -> TokenStream {
+        let descriptor_name = format_ident!("{}_crate_{}", BINDGEN_DESCRIBE_PREFIX, self.unique_suffix());
+        let cfg_attr = cfg_attr(self.cfg());
+
+        quote! {
+            #cfg_attr
+            #[no_mangle]
+            #[allow(non_snake_case)]
+            pub fn #descriptor_name() -> ::dotnet_bindgen::core::BindgenExportDescriptor {
+                ::dotnet_bindgen::core::BindgenExportDescriptor::Crate(
+                    ::dotnet_bindgen::core::BindgenCrateDescriptor {
+                        name: env!("CARGO_PKG_NAME").to_string(),
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                    }
+                )
+            }
+        }
+    }
 }
 
 impl ToTokens for Export {
@@ -279,7 +727,11 @@ impl ToTokens for Export {
         match self {
             Export::Func(f) => f.to_tokens(tokens),
             Export::Struct(s) => s.to_tokens(tokens),
+            Export::Opaque(o) => o.to_tokens(tokens),
+            Export::Enum(e) => e.to_tokens(tokens),
         };
+
+        self.crate_descriptor_func().to_tokens(tokens);
     }
 }
 
@@ -295,30 +747,61 @@ impl ToTokens for Program {
     }
 }
 
-trait MacroParse {
-    fn macro_parse(&self, program: &mut Program) -> Result<(), Diagnostic>;
+/// A `#[cfg(..)]` attribute to place on each generated item, or nothing if the export is ungated.
+fn cfg_attr(cfg: &Option<TokenStream>) -> TokenStream {
+    match cfg {
+        Some(cfg) => quote! { #[cfg(#cfg)] },
+        None => TokenStream::new(),
+    }
+}
+
+/// An expression constructing the `cfg` field of a descriptor
+fn cfg_descriptor_frag(cfg: &Option<TokenStream>) -> TokenStream {
+    match cfg {
+        Some(cfg) => {
+            let cfg_string = cfg.to_string();
+            quote! { Some(#cfg_string.to_string()) }
+        }
+        None => quote! { None },
+    }
+}
+
+/// An expression constructing an optional string field of a descriptor
+fn optional_string_frag(value: &Option<String>) -> TokenStream {
+    match value {
+        Some(value) => quote! { Some(#value.to_string()) },
+        None => quote! { None },
+    }
 }
 
-pub fn expand(_attrs: TokenStream, tokens: TokenStream) -> Result<TokenStream, Diagnostic> {
+trait MacroParse<Ctx> {
+    fn macro_parse(&self, program: &mut Program, ctx: Ctx) -> Result<(), Diagnostic>;
+}
+
+pub fn expand(attrs: TokenStream, tokens: TokenStream) -> Result<TokenStream, Diagnostic> {
     let mut program = Program {
         exports: Vec::new(),
     };
 
+    let attrs = BindgenAttrs::parse(attrs)?;
     let item = syn::parse2::<syn::Item>(tokens)?;
-    item.macro_parse(&mut program)?;
+    item.macro_parse(&mut program, &attrs)?;
 
     let mut tokens = proc_macro2::TokenStream::new();
+    cfg_attr(&attrs.cfg).to_tokens(&mut tokens);
     item.to_tokens(&mut tokens);
     program.to_tokens(&mut tokens);
 
     Ok(tokens)
 }
 
-impl MacroParse for syn::Item {
-    fn macro_parse(&self, program: &mut Program) -> Result<(), Diagnostic> {
+impl MacroParse<&BindgenAttrs> for syn::Item {
+    fn macro_parse(&self, program: &mut Program, attrs: &BindgenAttrs) -> Result<(), Diagnostic> {
         match self {
-            syn::Item::Fn(f) => f.macro_parse(program),
-            syn::Item::Struct(s) => s.macro_parse(program),
+            syn::Item::Fn(f) => f.macro_parse(program, attrs),
+            syn::Item::Struct(s) => s.macro_parse(program, attrs),
+            syn::Item::Enum(e) => e.macro_parse(program, attrs),
+            syn::Item::ForeignMod(m) => m.macro_parse(program, attrs),
             _ => Err(Diagnostic::spanned_error(
                 self,
                 "Can't generate binding metadata for this",
@@ -327,8 +810,17 @@ impl MacroParse for syn::Item {
     }
 }
 
-impl MacroParse for syn::ItemFn {
-    fn macro_parse(&self, program: &mut Program) -> Result<(), Diagnostic> {
+impl MacroParse<&BindgenAttrs> for syn::ItemFn {
+    fn macro_parse(&self, program: &mut Program, attrs: &BindgenAttrs) -> Result<(), Diagnostic> {
+        attrs.check_allowed("functions", &[
+            "cfg", "platforms", "out", "no_try", "body", "release", "bench", "roundtrip", "charset",
+            "error_enum", "namespace", "class",
+        ])?;
+
+        if let Some(param) = self.sig.generics.params.iter().find(|p| !matches!(p, syn::GenericParam::Lifetime(_))) {
+            bail_span!(param, "Can't generate binding metadata for generic functions");
+        }
+
         let mut arguments = Vec::new();
 
         for arg in self.sig.inputs.iter() {
@@ -338,52 +830,361 @@ impl MacroParse for syn::ItemFn {
                 }
                 syn::FnArg::Typed(pat_type) => {
                     let name = parse_pat(&pat_type.pat)?;
-                    let ty = *pat_type.ty.clone();
-                    ExportedFunctionArg { name, ty }
+                    if is_owned_string(&pat_type.ty) {
+                        bail_span!(pat_type.ty, "{}", OWNED_STRING_ARG_ERROR);
+                    }
+                    // The thunk doesn't declare the function's lifetimes, so can only refer to
+                    // them as elided ones
+                    let ty = ElideLifetimes.fold_type(*pat_type.ty.clone());
+                    let out = attrs.out.contains(&name);
+                    if out && !is_mut_ref(&ty) {
+                        bail_span!(ty, "Only `&mut` arguments can be marked as out");
+                    }
+                    let charset = attrs.charsets
+                        .iter()
+                        .find(|(arg, _)| arg == &name)
+                        .map(|(_, charset)| charset.clone());
+                    ExportedFunctionArg { name, ty, out, charset }
                 }
             });
         }
 
+        if attrs.release && arguments.len() != 1 {
+            bail_span!(self.sig.inputs, "Release functions must take exactly one argument, the handle to free");
+        }
+
+        if attrs.roundtrip {
+            let returns_arg_ty = match (arguments.as_slice(), &self.sig.output) {
+                ([arg], syn::ReturnType::Type(_, ty)) => {
+                    arg.ty.to_token_stream().to_string() == ty.to_token_stream().to_string()
+                }
+                _ => false,
+            };
+            if !returns_arg_ty {
+                bail_span!(self.sig, "Round trip functions must take exactly one argument, and return the same type");
+            }
+        }
+
+        for (charset_arg, _) in &attrs.charsets {
+            if !arguments.iter().any(|a| &a.name == charset_arg) {
+                bail_span!(charset_arg, "No argument with this name");
+            }
+        }
+
+        for out in &attrs.out {
+            if !arguments.iter().any(|a| &a.name == out) {
+                bail_span!(out, "No argument with this name");
+            }
+        }
+
         let name = self.sig.ident.clone();
+        // A diverging function is bound as returning nothing, as it never hands anything back
+        let diverges = matches!(&self.sig.output, syn::ReturnType::Type(_, ty) if matches!(**ty, syn::Type::Never(_)));
         let return_ty: Option<syn::Type> = match &self.sig.output {
             syn::ReturnType::Default => None,
+            syn::ReturnType::Type(..) if diverges => None,
             syn::ReturnType::Type(_arrow, ty) => Some(*ty.clone()),
         };
 
+        if let Some(borrow) = return_ty.as_ref().and_then(find_non_static_borrow) {
+            bail_span!(borrow, "{}", BORROWED_RETURN_ERROR);
+        }
+
+        if let Some(ty) = return_ty.as_ref().filter(|ty| is_owned_string(ty)) {
+            bail_span!(ty, "{}", OWNED_STRING_RETURN_ERROR);
+        }
+
         program.exports.push(Export::Func(ExportedFunction {
             name,
             arguments,
             return_ty,
+            cfg: attrs.cfg.clone(),
+            platforms: attrs.platforms.clone(),
+            no_try: attrs.no_try,
+            body: attrs.body.clone(),
+            release: attrs.release,
+            bench: attrs.bench,
+            roundtrip: attrs.roundtrip,
+            must_use: parse_must_use(&self.attrs),
+            diverges,
+            error_enum: attrs.error_enum.clone(),
+            import: None,
+            namespace: attrs.namespace.clone(),
+            class: attrs.class.clone(),
         }));
 
         Ok(())
     }
 }
 
-impl MacroParse for syn::ItemStruct {
-    fn macro_parse(&self, program: &mut Program) -> Result<(), Diagnostic> {
+impl MacroParse<&BindgenAttrs> for syn::ItemStruct {
+    fn macro_parse(&self, program: &mut Program, attrs: &BindgenAttrs) -> Result<(), Diagnostic> {
         let name = self.ident.clone();
 
-        let fields = match &self.fields {
-            syn::Fields::Named(n) => parse_named_fields(&n),
+        attrs.check_allowed("structs", &["cfg", "opaque", "native_marshalling", "as", "string_buffers", "namespace"])?;
+
+        if attrs.opaque {
+            // Opaque types have no fields, and never cross the boundary by value
+            attrs.check_allowed("opaque types", &["cfg", "opaque", "namespace"])?;
+
+            if !self.generics.params.is_empty() {
+                bail_span!(self.generics, "Can't generate binding metadata for generic opaque types");
+            }
+
+            program.exports.push(Export::Opaque(ExportedOpaque {
+                name,
+                cfg: attrs.cfg.clone(),
+                namespace: attrs.namespace.clone(),
+            }));
+
+            return Ok(());
+        }
+
+        let mut fields = match &self.fields {
+            syn::Fields::Named(n) => parse_named_fields(n),
             _ => Err(Diagnostic::spanned_error(
                 self,
                 "Can only structs with named fields"
             ))
         }?;
 
+        if let Some(as_type) = &attrs.as_type {
+            validate_as_type_fields(&self.ident, as_type, &fields)?;
+        }
+
+        let mut string_buffer_width = None;
+        for string_buffer in &attrs.string_buffers {
+            let field = match fields.iter_mut().find(|f| &f.name == string_buffer) {
+                Some(field) => field,
+                None => bail_span!(string_buffer, "No field with this name"),
+            };
+
+            let width = match parse_string_buffer_width(&field.ty) {
+                Some(width) => width,
+                None => bail_span!(field.ty, "String buffers must be `[u8; N]` or `[u16; N]` arrays"),
+            };
+
+            // The encoding of every string buffer is given by the CharSet of the struct as a whole
+            if string_buffer_width.get_or_insert(width) != &width {
+                bail_span!(field.ty, "All the string buffers of a struct must have the same element type");
+            }
+
+            field.string_buffer = true;
+        }
+
         let span = self.ident.span();
 
         program.exports.push(Export::Struct(ExportedStruct {
             name,
             fields,
             span,
+            cfg: attrs.cfg.clone(),
+            native_marshalling: attrs.native_marshalling,
+            as_type: attrs.as_type.clone(),
+            namespace: attrs.namespace.clone(),
         }));
 
         Ok(())
     }
 }
 
+/// Checks that a struct given `#[dotnet_bindgen(as = "..")]` has the fields its conversions need.
+fn validate_as_type_fields(
+    ident: &syn::Ident,
+    as_type: &str,
+    fields: &[ExportedStructField],
+) -> Result<(), Diagnostic> {
+    match as_type {
+        "TimeSpan" => {
+            let has_field = |names: &[&str]| fields.iter().any(|f| names.contains(&f.name.to_string().as_str()));
+            if fields.len() != 2 || !has_field(TIMESPAN_SECONDS_FIELDS) || !has_field(TIMESPAN_NANOS_FIELDS) {
+                bail_span!(
+                    ident,
+                    "Structs converted to a TimeSpan must have exactly two integer fields, `secs` and `nanos`"
+                );
+            }
+            Ok(())
+        }
+        _ => unreachable!("Unknown as types are rejected when parsing the attribute"),
+    }
+}
+
+impl MacroParse<&BindgenAttrs> for syn::ItemForeignMod {
+    fn macro_parse(&self, program: &mut Program, attrs: &BindgenAttrs) -> Result<(), Diagnostic> {
+        attrs.check_allowed("extern blocks", &["cfg", "library", "namespace", "class"])?;
+
+        // The binary is only linked against the library, so its name can't be recovered from it
+        let library = match &attrs.library {
+            Some(library) => library.clone(),
+            None => bail_span!(self.abi, "Extern blocks must name the library they import from, eg `library = \"m\"`"),
+        };
+
+        if let Some(abi) = &self.abi.name {
+            if abi.value() != "C" {
+                bail_span!(abi, "Can only generate binding metadata for extern \"C\" blocks");
+            }
+        }
+
+        for item in &self.items {
+            let f = match item {
+                syn::ForeignItem::Fn(f) => f,
+                _ => bail_span!(item, "Can only generate binding metadata for the functions of extern blocks"),
+            };
+
+            if let Some(variadic) = &f.sig.variadic {
+                bail_span!(variadic, "Can't generate binding metadata for variadic functions");
+            }
+
+            let mut arguments = Vec::new();
+            for arg in f.sig.inputs.iter() {
+                arguments.push(match arg {
+                    syn::FnArg::Receiver(r) => bail_span!(r, "Can't generate binding metadata for methods"),
+                    syn::FnArg::Typed(pat_type) if is_owned_string(&pat_type.ty) => {
+                        bail_span!(pat_type.ty, "{}", OWNED_STRING_ARG_ERROR)
+                    }
+                    syn::FnArg::Typed(pat_type) => ExportedFunctionArg {
+                        name: parse_pat(&pat_type.pat)?,
+                        ty: *pat_type.ty.clone(),
+                        out: false,
+                        charset: None,
+                    },
+                });
+            }
+
+            let return_ty = match &f.sig.output {
+                syn::ReturnType::Default => None,
+                syn::ReturnType::Type(_, ty) if matches!(**ty, syn::Type::Never(_)) => {
+                    bail_span!(ty, "Can't generate binding metadata for diverging imported functions")
+                }
+                syn::ReturnType::Type(_arrow, ty) => Some(*ty.clone()),
+            };
+
+            if let Some(borrow) = return_ty.as_ref().and_then(find_non_static_borrow) {
+                bail_span!(borrow, "{}", BORROWED_RETURN_ERROR);
+            }
+
+            if let Some(ty) = return_ty.as_ref().filter(|ty| is_owned_string(ty)) {
+                bail_span!(ty, "{}", OWNED_STRING_RETURN_ERROR);
+            }
+
+            let link_name = parse_link_name(&f.attrs).unwrap_or_else(|| f.sig.ident.to_string());
+
+            program.exports.push(Export::Func(ExportedFunction {
+                name: f.sig.ident.clone(),
+                arguments,
+                return_ty,
+                cfg: attrs.cfg.clone(),
+                platforms: Vec::new(),
+                no_try: false,
+                body: None,
+                release: false,
+                bench: false,
+                roundtrip: false,
+                must_use: parse_must_use(&f.attrs),
+                diverges: false,
+                error_enum: None,
+                import: Some(ImportedSymbol { library: library.clone(), link_name }),
+                namespace: attrs.namespace.clone(),
+                class: attrs.class.clone(),
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+/// The integer types an enum may be represented as, eg `#[repr(u8)]`
+const ENUM_REPR_TYPES: &[&str] = &["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64"];
+
+impl MacroParse<&BindgenAttrs> for syn::ItemEnum {
+    fn macro_parse(&self, program: &mut Program, attrs: &BindgenAttrs) -> Result<(), Diagnostic> {
+        let name = self.ident.clone();
+
+        attrs.check_allowed("enums", &["cfg", "namespace"])?;
+
+        if !self.generics.params.is_empty() {
+            bail_span!(self.generics, "Can't generate binding metadata for generic enums");
+        }
+
+        let repr = parse_enum_repr(&self.attrs)
+            .ok_or_else(|| err_span!(
+                self.ident,
+                "Enums must have an explicit integer representation, eg `#[repr(i32)]`"
+            ))?;
+
+        let variants = self.variants
+            .iter()
+            .map(|v| ExportedEnumVariant {
+                name: v.ident.clone(),
+                discriminant: v.discriminant.as_ref().map(|(_eq, expr)| expr.clone()),
+                has_fields: !v.fields.is_empty(),
+            })
+            .collect();
+
+        program.exports.push(Export::Enum(ExportedEnum {
+            name,
+            repr,
+            variants,
+            cfg: attrs.cfg.clone(),
+            namespace: attrs.namespace.clone(),
+        }));
+
+        Ok(())
+    }
+}
+
+/// The reason given to a `#[must_use]` attribute, or an empty string if there isn't one
+fn parse_must_use(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("must_use"))
+        .find_map(|a| match a.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(s), .. })) => Some(s.value()),
+            Ok(_) => Some(String::new()),
+            Err(_) => None,
+        })
+}
+
+/// The width in bits of the code units of a `[u8; N]` or `[u16; N]` string buffer
+fn parse_string_buffer_width(ty: &syn::Type) -> Option<u8> {
+    match ty {
+        syn::Type::Array(a) => match &*a.elem {
+            syn::Type::Path(p) if p.path.is_ident("u8") => Some(8),
+            syn::Type::Path(p) if p.path.is_ident("u16") => Some(16),
+            _ => None,
+        },
+        syn::Type::Paren(p) => parse_string_buffer_width(&p.elem),
+        syn::Type::Group(g) => parse_string_buffer_width(&g.elem),
+        _ => None,
+    }
+}
+
+/// Finds the symbol an imported function is linked against, if it is renamed by `#[link_name]`
+fn parse_link_name(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|a| match a.parse_meta() {
+        Ok(syn::Meta::NameValue(syn::MetaNameValue { path, lit: syn::Lit::Str(s), .. }))
+            if path.is_ident("link_name") => Some(s.value()),
+        _ => None,
+    })
+}
+
+/// Finds the integer type in a `#[repr(..)]` attribute, eg `#[repr(C, u8)]` -> `u8`
+fn parse_enum_repr(attrs: &[syn::Attribute]) -> Option<proc_macro2::Ident> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("repr"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(syn::Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            syn::NestedMeta::Meta(syn::Meta::Path(p)) => p.get_ident().cloned(),
+            _ => None,
+        })
+        .find(|ident| ENUM_REPR_TYPES.contains(&ident.to_string().as_str()))
+}
+
 fn parse_named_fields(fields: &syn::FieldsNamed) -> Result<Vec<ExportedStructField>, Diagnostic> {
     let mut fields_parsed = Vec::new();
     for field in fields.named.iter() {
@@ -397,29 +1198,248 @@ fn parse_named_fields(fields: &syn::FieldsNamed) -> Result<Vec<ExportedStructFie
             name,
             ty,
             span,
+            string_buffer: false,
         })
     }
 
     Ok(fields_parsed)
 }
 
+fn is_mut_ref(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Reference(r) => r.mutability.is_some(),
+        syn::Type::Paren(p) => is_mut_ref(&p.elem),
+        syn::Type::Group(g) => is_mut_ref(&g.elem),
+        _ => false,
+    }
+}
+
+/// Nothing on the other side of the boundary can hold a borrow to account for its lifetime
+const BORROWED_RETURN_ERROR: &str =
+    "Can't return borrowed data across the boundary, return a raw pointer and document who owns it instead";
+
+const OWNED_STRING_ARG_ERROR: &str = "Strings can't be passed by value, take a `&str` instead";
+
+/// The C# marshaller would free the returned string with its own allocator, not Rust's
+const OWNED_STRING_RETURN_ERROR: &str = "Can't return an owned String across the boundary, as nothing can free it";
+
+/// Whether the type is a `String`, which the user most likely meant to pass as a `&str`
+fn is_owned_string(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(p) if p.qself.is_none() => p.path.segments.last().is_some_and(|segment| {
+            segment.ident == "String" && segment.arguments.is_empty()
+        }),
+        syn::Type::Paren(p) => is_owned_string(&p.elem),
+        syn::Type::Group(g) => is_owned_string(&g.elem),
+        _ => false,
+    }
+}
+
+/// Replaces every named lifetime other than `'static` with `'_`
+struct ElideLifetimes;
+
+impl Fold for ElideLifetimes {
+    fn fold_lifetime(&mut self, lifetime: syn::Lifetime) -> syn::Lifetime {
+        match lifetime.ident == "static" {
+            true => lifetime,
+            false => syn::Lifetime::new("'_", lifetime.span()),
+        }
+    }
+}
+
+/// Finds a borrow in the given type which isn't `'static`, eg the `&'a Bar` of `Option<&'a Bar>`
+fn find_non_static_borrow(ty: &syn::Type) -> Option<&syn::Type> {
+    let is_static = |l: &syn::Lifetime| l.ident == "static";
+    match ty {
+        syn::Type::Reference(r) if !r.lifetime.as_ref().is_some_and(is_static) => Some(ty),
+        syn::Type::Reference(r) => find_non_static_borrow(&r.elem),
+        syn::Type::Paren(p) => find_non_static_borrow(&p.elem),
+        syn::Type::Group(g) => find_non_static_borrow(&g.elem),
+        syn::Type::Array(a) => find_non_static_borrow(&a.elem),
+        syn::Type::Slice(s) => find_non_static_borrow(&s.elem),
+        syn::Type::Tuple(t) => t.elems.iter().find_map(find_non_static_borrow),
+        syn::Type::Path(p) => p.path.segments.iter().find_map(|segment| match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                syn::GenericArgument::Lifetime(l) if !is_static(l) => Some(ty),
+                syn::GenericArgument::Type(t) => find_non_static_borrow(t),
+                _ => None,
+            }),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
 fn parse_pat(pat: &syn::Pat) -> Result<proc_macro2::Ident, Diagnostic> {
     match pat {
-        syn::Pat::Ident(pat_ident) => parse_pat_ident(&pat_ident),
+        syn::Pat::Ident(pat_ident) => parse_pat_ident(pat_ident),
         _ => bail_span!(pat, "Can't generate binding metadata for this pattern"),
     }
 }
 
 fn parse_pat_ident(pat_ident: &syn::PatIdent) -> Result<proc_macro2::Ident, Diagnostic> {
-    match &pat_ident.by_ref {
-        Some(r) => bail_span!(r, "Can't generate binding metadata for ref types"),
-        None => (),
+    if let Some(r) = &pat_ident.by_ref {
+        bail_span!(r, "Can't generate binding metadata for ref types")
     };
 
-    match &pat_ident.subpat {
-        Some((_at, pat)) => bail_span!(pat, "Can't generate binding metadata for subpatterns"),
-        None => (),
+    if let Some((_at, pat)) = &pat_ident.subpat {
+        bail_span!(pat, "Can't generate binding metadata for subpatterns")
     };
 
     Ok(pat_ident.ident.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The compile error the attribute expands to on an item it rejects
+    fn expansion_error(item: TokenStream) -> String {
+        expansion_error_with(TokenStream::new(), item)
+    }
+
+    /// The compile error the attribute expands to when given options it rejects
+    fn expansion_error_with(attrs: TokenStream, item: TokenStream) -> String {
+        expand(attrs, item).unwrap_err().to_token_stream().to_string()
+    }
+
+    fn compile_error(message: &str) -> String {
+        quote!(compile_error! { #message }).to_string()
+    }
+
+    #[test]
+    fn string_arguments_are_rejected() {
+        assert_eq!(
+            expansion_error(quote!(fn greet(name: String) {})),
+            compile_error(OWNED_STRING_ARG_ERROR),
+        );
+        assert_eq!(
+            expansion_error(quote!(fn greet(name: std::string::String) {})),
+            compile_error(OWNED_STRING_ARG_ERROR),
+        );
+    }
+
+    #[test]
+    fn string_returns_are_rejected() {
+        assert_eq!(
+            expansion_error(quote!(fn name() -> String { String::new() })),
+            compile_error(OWNED_STRING_RETURN_ERROR),
+        );
+    }
+
+    #[test]
+    fn generic_functions_are_rejected() {
+        assert_eq!(
+            expansion_error(quote!(fn first<T: Copy>(values: &[T]) -> T { values[0] })),
+            compile_error("Can't generate binding metadata for generic functions"),
+        );
+    }
+
+    #[test]
+    fn lifetime_parameters_are_elided() {
+        let expanded = expand(TokenStream::new(), quote!(fn len<'a>(name: &'a str) -> usize { name.len() }))
+            .unwrap()
+            .to_string();
+        assert!(!expanded.contains("compile_error"));
+        assert!(expanded.contains("& '_ str"));
+    }
+
+    #[test]
+    fn options_are_only_allowed_on_their_items() {
+        assert_eq!(
+            expansion_error_with(quote!(library = "m"), quote!(fn f() {})),
+            compile_error("The library option can't be given to functions"),
+        );
+        assert_eq!(
+            expansion_error_with(quote!(cfg(unix), no_try), quote!(struct S { a: i32 })),
+            compile_error("The no_try option can't be given to structs"),
+        );
+        assert_eq!(
+            expansion_error_with(quote!(opaque, as = "TimeSpan"), quote!(struct S { a: i32 })),
+            compile_error("The as option can't be given to opaque types"),
+        );
+        assert_eq!(
+            expansion_error_with(quote!(body = "return;"), quote!(extern "C" { fn f(); })),
+            compile_error("The body option can't be given to extern blocks"),
+        );
+        assert_eq!(
+            expansion_error_with(quote!(namespace = "A", opaque), quote!(#[repr(u8)] enum E { A })),
+            compile_error("The opaque option can't be given to enums"),
+        );
+    }
+
+    #[test]
+    fn c_like_enums_cross_as_their_repr() {
+        let expanded = expand(TokenStream::new(), quote!(#[repr(u8)] enum Colour { Red = 1, Green }))
+            .unwrap()
+            .to_string();
+
+        assert!(expanded.contains(&quote!(impl ::dotnet_bindgen::core::BindgenAbiConvert for Colour).to_string()));
+        assert!(expanded.contains(&quote!(type AbiType = u8;).to_string()));
+        assert!(expanded.contains("is not a valid discriminant for"));
+        assert!(expanded.contains("c_like : true"));
+    }
+
+    #[test]
+    fn data_carrying_enums_only_describe_their_discriminants() {
+        let expanded = expand(TokenStream::new(), quote!(#[repr(i32)] enum Message { Quit = -1, Write(u8) }))
+            .unwrap()
+            .to_string();
+
+        assert!(!expanded.contains("BindgenAbiConvert"));
+        assert!(expanded.contains("c_like : false"));
+        assert!(expanded.contains("BindgenEnumVariantDescriptor"));
+    }
+
+    #[test]
+    fn namespace_and_class_reach_the_descriptor() {
+        let expanded = expand(quote!(namespace = "A.B", class = "C"), quote!(fn f() {}))
+            .unwrap()
+            .to_string();
+
+        assert!(expanded.contains(&quote!(namespace: Some("A.B".to_string()),).to_string()));
+        assert!(expanded.contains(&quote!(class: Some("C".to_string()),).to_string()));
+    }
+
+    #[test]
+    fn class_is_only_given_to_functions() {
+        assert_eq!(
+            expansion_error_with(quote!(class = "C"), quote!(struct S { a: i32 })),
+            compile_error("The class option can't be given to structs"),
+        );
+        assert_eq!(
+            expansion_error_with(quote!(class = "C"), quote!(#[repr(u8)] enum E { A })),
+            compile_error("The class option can't be given to enums"),
+        );
+    }
+
+    #[test]
+    fn borrowed_returns_are_rejected() {
+        assert_eq!(
+            expansion_error(quote!(fn first(values: &[u8]) -> &u8 { &values[0] })),
+            compile_error(BORROWED_RETURN_ERROR),
+        );
+        assert_eq!(
+            expansion_error(quote!(fn find<'a>(values: &'a [u8]) -> Option<&'a u8> { values.first() })),
+            compile_error(BORROWED_RETURN_ERROR),
+        );
+        assert!(expand(TokenStream::new(), quote!(fn name() -> &'static u8 { &0 })).is_ok());
+    }
+
+    #[test]
+    fn functions_expand_to_a_thunk_and_a_descriptor() {
+        let expanded = expand(TokenStream::new(), quote!(fn add(a: i32, b: i32) -> i64 { a as i64 + b as i64 }))
+            .unwrap()
+            .to_string();
+
+        assert!(expanded.contains(&quote!(fn add(a: i32, b: i32) -> i64 { a as i64 + b as i64 }).to_string()));
+        assert!(expanded.contains(&quote!(#[no_mangle] pub extern "C" fn __bindgen_thunk_add).to_string()));
+        assert!(expanded.contains(&quote!(let ret = add(a, b);).to_string()));
+        assert!(expanded.contains(
+            &quote!(#[no_mangle] pub fn __bindgen_describe_func_add() -> ::dotnet_bindgen::core::BindgenExportDescriptor)
+                .to_string()
+        ));
+        assert!(expanded.contains(&quote!(real_name: "add".to_string(),).to_string()));
+        assert!(expanded.contains(&quote!(thunk_name: "__bindgen_thunk_add".to_string(),).to_string()));
+    }
+}
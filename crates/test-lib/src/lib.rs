@@ -25,8 +25,8 @@ fn slice_arg(slice: &[i32]) {
 #[dotnet_bindgen]
 #[derive(Debug)]
 pub struct SimpleStruct {
-    field_1: i32,
-    field_2: u64,
+    pub field_1: i32,
+    pub field_2: u64,
 }
 
 #[dotnet_bindgen]
@@ -34,7 +34,409 @@ fn struct_arg_val(arg: SimpleStruct) {
     dbg!(arg);
 }
 
+#[dotnet_bindgen]
+fn struct_arg_ref(arg: &SimpleStruct) {
+    dbg!(arg);
+}
+
 #[dotnet_bindgen]
 fn bool_arg(arg: bool) {
     dbg!(arg);
-}
\ No newline at end of file
+}
+
+#[dotnet_bindgen(cfg(debug_assertions))]
+fn debug_only(arg: i32) {
+    dbg!(arg);
+}
+
+#[dotnet_bindgen(cfg(not(debug_assertions)))]
+fn release_only(arg: i32) {
+    dbg!(arg);
+}
+
+#[dotnet_bindgen]
+fn optional_ref_arg(arg: Option<&i32>) -> i32 {
+    arg.copied().unwrap_or(-1)
+}
+
+#[dotnet_bindgen]
+fn optional_struct_ref_args(scale: i32, first: Option<&SimpleStruct>, second: Option<&SimpleStruct>) {
+    dbg!(scale, first, second);
+}
+
+#[dotnet_bindgen(opaque)]
+pub struct Counter {
+    count: i32,
+}
+
+#[dotnet_bindgen]
+fn counter_new() -> *mut Counter {
+    Box::into_raw(Box::new(Counter { count: 0 }))
+}
+
+#[dotnet_bindgen]
+fn counter_add(counter: *mut Counter, amount: i32) -> *mut Counter {
+    unsafe { (*counter).count += amount };
+    counter
+}
+
+#[dotnet_bindgen(release)]
+fn counter_free(counter: *mut Counter) -> i32 {
+    let counter = unsafe { Box::from_raw(counter) };
+    counter.count
+}
+
+#[dotnet_bindgen(platforms("windows", "linux"))]
+fn windows_or_linux_only(arg: i32) {
+    dbg!(arg);
+}
+
+#[dotnet_bindgen]
+fn ref_args(input: &i32, accumulator: &mut i64) {
+    *accumulator += *input as i64;
+}
+
+#[dotnet_bindgen(out(quotient, remainder))]
+fn div_rem(dividend: u32, divisor: u32, quotient: &mut u32, remainder: &mut u32) {
+    *quotient = dividend / divisor;
+    *remainder = dividend % divisor;
+}
+
+#[dotnet_bindgen]
+#[repr(u8)]
+#[derive(Debug)]
+pub enum Colour {
+    Red = 1,
+    Green,
+    Blue = 10,
+}
+
+#[dotnet_bindgen]
+fn colour_arg(colour: Colour) -> Colour {
+    dbg!(&colour);
+    colour
+}
+
+#[dotnet_bindgen]
+#[repr(C)]
+#[derive(Debug)]
+pub struct Pixel {
+    pub visible: bool,
+    pub colour: Colour,
+    pub alpha: u8,
+}
+
+#[dotnet_bindgen]
+fn pixel_arg(pixel: Pixel) -> Pixel {
+    dbg!(&pixel);
+    pixel
+}
+
+#[dotnet_bindgen]
+#[repr(i32)]
+pub enum Message {
+    Quit = -1,
+    Move { x: i32, y: i32 },
+    Write(u8) = 7,
+    ChangeColour(Colour),
+}
+
+#[dotnet_bindgen]
+fn parse_digit(digit: u8, value: &mut u8) -> bool {
+    match (digit as char).to_digit(10) {
+        Some(d) => {
+            *value = d as u8;
+            true
+        }
+        None => false,
+    }
+}
+
+#[dotnet_bindgen(no_try)]
+fn increment_if_even(value: &mut u32) -> bool {
+    let even = value.is_multiple_of(2);
+    if even {
+        *value += 1;
+    }
+    even
+}
+
+#[dotnet_bindgen(body = r#"
+    if (arg < 0)
+    {
+        throw new ArgumentOutOfRangeException(nameof(arg));
+    }
+
+    return __bindgen_thunk_custom_body(arg);
+"#)]
+fn custom_body(arg: i32) -> i32 {
+    arg * 2
+}
+
+#[dotnet_bindgen(out(id))]
+fn get_id(id: &mut [u8; 16]) {
+    for (idx, byte) in id.iter_mut().enumerate() {
+        *byte = idx as u8;
+    }
+}
+
+#[dotnet_bindgen]
+fn greet(name: &str) {
+    println!("Hello, {}!", name);
+}
+
+#[dotnet_bindgen(charset(greeting = "ansi"))]
+fn greet_with(greeting: &std::ffi::CStr, name: &str) {
+    println!("{}, {}!", greeting.to_string_lossy(), name);
+}
+
+#[dotnet_bindgen]
+fn greet_wide(name: dotnet_bindgen::core::WideStr) {
+    println!("Hello, {}!", name.to_string_lossy());
+}
+
+#[dotnet_bindgen]
+#[must_use = "the checksum is the only result"]
+fn checksum(data: &[u8]) -> u32 {
+    data.iter().map(|&b| b as u32).sum()
+}
+
+#[dotnet_bindgen]
+fn offset_len(len: usize, delta: isize) -> usize {
+    (len as isize + delta) as usize
+}
+
+#[dotnet_bindgen]
+fn fatal_error(code: i32) -> ! {
+    std::process::exit(code)
+}
+
+#[dotnet_bindgen]
+fn blend_colour_components_with_weights(
+    first_red: u8,
+    first_green: u8,
+    first_blue: u8,
+    second_red: u8,
+    second_green: u8,
+    second_blue: u8,
+    weight: u8,
+) -> u32 {
+    let blend = |a: u8, b: u8| (a as u32 * (255 - weight as u32) + b as u32 * weight as u32) / 255;
+    (blend(first_red, second_red) << 16) | (blend(first_green, second_green) << 8) | blend(first_blue, second_blue)
+}
+
+#[dotnet_bindgen(native_marshalling)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct Timestamp {
+    pub seconds: i64,
+    pub nanos: u32,
+}
+
+#[dotnet_bindgen]
+fn timestamp_arg(timestamp: Timestamp) -> u64 {
+    timestamp.seconds as u64 * 1_000_000_000 + timestamp.nanos as u64
+}
+
+#[dotnet_bindgen(as = "TimeSpan")]
+#[repr(C)]
+#[derive(Debug)]
+pub struct Timeout {
+    pub secs: u64,
+    pub nanos: u32,
+}
+
+#[dotnet_bindgen]
+fn sleep_for(timeout: Timeout) {
+    std::thread::sleep(std::time::Duration::new(timeout.secs, timeout.nanos))
+}
+
+#[dotnet_bindgen]
+fn high_byte(value: u16) -> u8 {
+    (value >> 8) as u8
+}
+
+#[dotnet_bindgen]
+fn low_half(value: u32) -> u16 {
+    value as u16
+}
+
+#[dotnet_bindgen]
+#[repr(i32)]
+pub enum ConfigError {
+    Ok = 0,
+    NotFound = 1,
+    Malformed = 2,
+}
+
+#[dotnet_bindgen(error_enum = "ConfigError")]
+fn load_config(version: u32) -> i32 {
+    match version {
+        0 => ConfigError::NotFound as i32,
+        1 => ConfigError::Ok as i32,
+        _ => ConfigError::Malformed as i32,
+    }
+}
+
+#[dotnet_bindgen]
+fn half_abs(value: dotnet_bindgen::core::Half) -> dotnet_bindgen::core::Half {
+    dotnet_bindgen::core::Half::from_bits(value.to_bits() & 0x7fff)
+}
+
+#[dotnet_bindgen(roundtrip)]
+fn identity_i8(value: i8) -> i8 {
+    value
+}
+
+#[dotnet_bindgen(roundtrip)]
+fn identity_u64(value: u64) -> u64 {
+    value
+}
+
+#[dotnet_bindgen(roundtrip)]
+fn identity_usize(value: usize) -> usize {
+    value
+}
+
+#[dotnet_bindgen(roundtrip)]
+fn identity_bool(value: bool) -> bool {
+    value
+}
+
+#[dotnet_bindgen(roundtrip)]
+fn identity_half(value: dotnet_bindgen::core::Half) -> dotnet_bindgen::core::Half {
+    value
+}
+
+// Only ever called from the bindings, not from rust
+#[allow(dead_code)]
+#[dotnet_bindgen(library = "libc")]
+extern "C" {
+    #[link_name = "abs"]
+    fn c_abs(value: i32) -> i32;
+}
+
+#[dotnet_bindgen(string_buffers(name))]
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeviceInfo {
+    pub id: u32,
+    pub name: [u8; 32],
+}
+
+#[dotnet_bindgen]
+fn device_name_len(info: DeviceInfo) -> usize {
+    info.name.iter().position(|&c| c == 0).unwrap_or(info.name.len())
+}
+
+#[dotnet_bindgen]
+fn dot_product(lhs: &[i32], rhs: &[i32]) -> i32 {
+    lhs.iter().zip(rhs).map(|(l, r)| l * r).sum()
+}
+
+#[dotnet_bindgen]
+fn add(a: i32, b: i32) -> i64 {
+    a as i64 + b as i64
+}
+
+#[dotnet_bindgen]
+fn scale(x: f64) -> f64 {
+    x * 2.0
+}
+
+#[dotnet_bindgen(roundtrip)]
+fn f32_roundtrip(value: f32) -> f32 {
+    value
+}
+
+#[dotnet_bindgen(namespace = "TestLib.Timing")]
+#[repr(C)]
+#[derive(Debug)]
+pub struct Tick {
+    pub count: u64,
+}
+
+#[dotnet_bindgen(namespace = "TestLib.Timing", class = "Clock")]
+fn next_tick(tick: Tick) -> Tick {
+    Tick { count: tick.count + 1 }
+}
+
+#[dotnet_bindgen]
+fn xor_bytes(buf: *const u8, len: u64) -> u8 {
+    if buf.is_null() {
+        return 0;
+    }
+
+    // The caller guarantees that buf points to len readable bytes
+    let bytes = unsafe { std::slice::from_raw_parts(buf, len as usize) };
+    bytes.iter().fold(0, |acc, b| acc ^ b)
+}
+
+#[dotnet_bindgen]
+fn first_arg(argv: *mut *const u8) -> *const u8 {
+    match argv.is_null() {
+        true => std::ptr::null(),
+        false => unsafe { *argv },
+    }
+}
+
+#[dotnet_bindgen]
+#[allow(clippy::needless_lifetimes)]
+fn greet_twice<'a>(name: &'a str) {
+    println!("Hello, {0}! Hello, {0}!", name);
+}
+
+pub mod geometry {
+    use dotnet_bindgen::dotnet_bindgen;
+
+    #[dotnet_bindgen]
+    #[derive(Debug)]
+    pub struct Point {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    #[dotnet_bindgen]
+    fn manhattan_length(point: Point) -> u32 {
+        point.x.unsigned_abs() + point.y.unsigned_abs()
+    }
+}
+
+pub mod analytics {
+    use dotnet_bindgen::dotnet_bindgen;
+
+    // Both functions pull in the same using, which sorts before the System usings
+    #[dotnet_bindgen(namespace = "Analytics")]
+    fn mean(values: &[f64]) -> f64 {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+
+    #[dotnet_bindgen(namespace = "Analytics")]
+    fn total(values: &[f64]) -> f64 {
+        values.iter().sum()
+    }
+}
+
+#[dotnet_bindgen(out(page))]
+fn fill_page(page: &mut [u8; 4096]) {
+    page.fill(0xff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dotnet_bindgen::core::BindgenAbiConvert;
+
+    #[test]
+    fn valid_discriminants_convert() {
+        assert!(matches!(Colour::from_abi_type(10), Colour::Blue));
+        assert_eq!(Colour::Green.to_abi_type(), 2);
+    }
+
+    // Inside a thunk this panic aborts the process, as it can't unwind into the C# caller
+    #[test]
+    #[should_panic(expected = "7 is not a valid discriminant for Colour")]
+    fn invalid_discriminants_panic() {
+        Colour::from_abi_type(7);
+    }
+}